@@ -46,3 +46,37 @@ fn new_comp_full(){
 
 
 
+
+#[test]
+fn set_group(){
+	let mut p=crate::Property::new_empty("EMAIL".to_string(), "a@b.c".to_string()).unwrap();
+	p.set_group("item1".to_string()).unwrap();
+	assert_eq!(p.group.as_ref().unwrap(), "ITEM1");
+	expect_err(p.set_group("item 1".to_string()),"group name \"item 1\" is invalid: character ' ' is not allowed");
+	p.group=Some("it.em".into());
+	expect_err(p.check(),"group name \"it.em\" is invalid: character '.' is not allowed");
+}
+
+#[test]
+fn text_value(){
+	let mut p=crate::Property::new_empty("NOTE".to_string(), "a\\, b\\; c\\nd".to_string()).unwrap();
+	assert_eq!(p.text_value(), "a, b; c\nd");
+	p.set_text_value("1,2;3\\4\r\n5");
+	assert_eq!(p.value, "1\\,2\\;3\\\\4\\n5");
+	assert_eq!(p.text_value(), "1,2;3\\4\n5");
+
+	let mut parser=crate::BorrowedParser::new("BEGIN:A\r\nNOTE:x\\,y\r\nEND:A\r\n");
+	assert_eq!(parser.next_component().unwrap().unwrap().properties[0].text_value(), "x,y");
+}
+
+#[test]
+fn structured_value(){
+	let mut p=crate::Property::new_empty("N".to_string(), "Public;John;Q.,R.;;Esq\\;".to_string()).unwrap();
+	assert_eq!(p.structured_value(), vec![vec!["Public"], vec!["John"], vec!["Q.", "R."], vec![""], vec!["Esq;"]]);
+	p.set_structured_value(&[vec!["O'Hara, Jr."], vec!["Ann"], vec![], vec!["Dr.", "Prof."]]);
+	assert_eq!(p.value, "O'Hara\\, Jr.;Ann;;Dr.,Prof.");
+	assert_eq!(p.structured_value(), vec![vec!["O'Hara, Jr."], vec!["Ann"], vec![""], vec!["Dr.", "Prof."]]);
+
+	let mut parser=crate::BorrowedParser::new("BEGIN:A\r\nCATEGORIES:a,b\\,c\r\nEND:A\r\n");
+	assert_eq!(parser.next_component().unwrap().unwrap().properties[0].structured_value(), vec![vec!["a", "b,c"]]);
+}
@@ -0,0 +1,55 @@
+use std::io::Result;
+
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::Component;
+use crate::encoder::{ComponentEncode, EncoderOptions};
+
+//AsyncEncoder writes components into an asynchronous writer. Every component is encoded into a buffer
+// first, exactly like the Encoder does, and then written out at once.
+pub struct AsyncEncoder<W> {
+	out: W,
+	buf: Vec<u8>,
+	options: EncoderOptions,
+}
+
+impl<W> AsyncEncoder<W> where W: AsyncWrite + Unpin {
+	pub fn new(writer: W) -> AsyncEncoder<W> {
+		Self::new_with_options(writer, EncoderOptions::default())
+	}
+
+	pub fn new_with_options(writer: W, options: EncoderOptions) -> AsyncEncoder<W> {
+		AsyncEncoder { out: writer, buf: Vec::new(), options }
+	}
+
+	pub fn options(&self) -> &EncoderOptions {
+		&self.options
+	}
+
+	pub async fn encode(&mut self, component: &Component) -> Result<()> {
+		self.buf.clear();
+		self.buf.encode_component_with_options(component, &self.options)?;
+		self.out.write_all(&self.buf).await
+	}
+
+	pub async fn encode_all(&mut self, component: &[Component]) -> Result<()> {
+		for c in component {
+			self.encode(c).await?;
+		}
+		Ok(())
+	}
+
+	pub async fn flush(&mut self) -> Result<()> {
+		self.out.flush().await
+	}
+
+	pub fn into_inner(self) -> W {
+		self.out
+	}
+}
+
+impl<W> From<W> for AsyncEncoder<W> where W: AsyncWrite + Unpin {
+	fn from(x: W) -> Self {
+		AsyncEncoder::new(x)
+	}
+}
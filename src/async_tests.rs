@@ -0,0 +1,149 @@
+use bytes::BytesMut;
+use futures_util::{FutureExt, SinkExt, StreamExt};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader, DuplexStream};
+use tokio_util::codec::{Decoder, FramedRead, FramedWrite};
+
+use crate::{AsyncEncoder, AsyncParser, ContentLineCodec, EncoderOptions, Limits, ParserOptions};
+use crate::test_helper::{assert_comp_equal, c, c2, expect_err, p2};
+
+//feed writes the input into a duplex stream in small chunks, so that lines and folds are split up.
+fn feed(input: &'static str) -> DuplexStream {
+	let (mut w, r) = tokio::io::duplex(8);
+	tokio::spawn(async move {
+		for chunk in input.as_bytes().chunks(3) {
+			w.write_all(chunk).await.unwrap();
+		}
+	});
+	r
+}
+
+#[tokio::test]
+async fn parse_components() {
+	let input = "BEGIN:comp\r\nFEATURE:Conten\r\n t:'!,;.'\r\nBEGIN:inner\r\nEND:inner\r\nEND:Comp\r\nBEGIN:porp\r\nEND:poRp\r\n";
+	let mut p = AsyncParser::new(BufReader::new(feed(input)));
+	let got = p.next_component().await.unwrap().unwrap();
+	assert_comp_equal(&got, &c("COMP", vec![p2("FEATURE", "Content:'!,;.'")], vec![c2("INNER")]));
+	let got = p.next_component().await.unwrap().unwrap();
+	assert_comp_equal(&got, &c2("PORP"));
+	assert!(p.next_component().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn parse_before_next_line() {
+	//the stream stays open, the first byte of the next line shows that the END line is complete
+	let (mut w, r) = tokio::io::duplex(1024);
+	w.write_all(b"BEGIN:a\r\nEND:a\r\nB").await.unwrap();
+	let mut p = AsyncParser::new(BufReader::new(r));
+	let got = p.next_component().now_or_never().expect("the component is complete").unwrap().unwrap();
+	assert_comp_equal(&got, &c2("A"));
+	//a continuation line could still follow
+	w.write_all(b"EGIN:b\r\nEND:b\r\n").await.unwrap();
+	assert!(p.next_component().now_or_never().is_none());
+	drop(w);
+	assert_comp_equal(&p.next_component().await.unwrap().unwrap(), &c2("B"));
+}
+
+#[tokio::test]
+async fn parse_stream_lenient() {
+	let input = "BEGIN:comp\nFEATURE:Conten\n t\n\nEND:Comp\nBEGIN:porp\nEND:poRp";
+	let p = AsyncParser::new_with_options(BufReader::new(feed(input)), ParserOptions::lenient());
+	let got: Vec<_> = p.map(|c| c.unwrap()).collect().await;
+	assert_eq!(got.len(), 2);
+	assert_comp_equal(&got[0], &c("COMP", vec![p2("FEATURE", "Content")], vec![]));
+	assert_comp_equal(&got[1], &c2("PORP"));
+}
+
+#[tokio::test]
+async fn parse_vcard21() {
+	let input = "BEGIN:VCARD\nNOTE;QUOTED-PRINTABLE:a=3D=\nb=\n=0D=0Ac\nEND:VCARD\n";
+	let mut p = AsyncParser::new_with_options(BufReader::new(feed(input)), ParserOptions::vcard21());
+	assert_comp_equal(&p.next_component().await.unwrap().unwrap(), &c("VCARD", vec![p2("NOTE", "a=b\r\nc")], vec![]));
+	assert!(p.next_component().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn parse_errors() {
+	let mut p = AsyncParser::new(BufReader::new(feed("BEGIN:co\r\nwas;x\r\n")));
+	expect_err(p.next_component().await, "line 2: \texpected '=': was;x<HERE>\n");
+
+	let mut p = AsyncParser::new(BufReader::new(feed("BEGIN:co\r\nwas:x\r\n")));
+	expect_err(p.next_component().await, "line 3: \tunexpected end of file or stream, expected END:CO\n");
+}
+
+#[tokio::test]
+async fn parse_recover() {
+	let input = "BEGIN:a\r\nwas;x\r\nBEGIN:inner\r\nEND:inner\r\nEND:a\r\nBEGIN:b\r\nEND:b\r\n";
+	let options = ParserOptions { recover_from_errors: true, ..ParserOptions::default() };
+	let mut p = AsyncParser::new_with_options(BufReader::new(feed(input)), options);
+	let e = p.next_component().await.unwrap_err();
+	assert_eq!(e.skipped_lines(), Some((1, 5)));
+	assert_comp_equal(&p.next_component().await.unwrap().unwrap(), &c2("B"));
+	assert!(p.next_component().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn parse_endless_line() {
+	let options = ParserOptions { limits: Limits { max_line_length: Some(100), ..Limits::default() }, ..ParserOptions::default() };
+	let input = || feed("BEGIN:a\r\nX:").chain(tokio::io::repeat(b'a'));
+	let mut p = AsyncParser::new_with_options(BufReader::new(input()), options.clone());
+	expect_err(p.next_component().await, "line 2: \tlimit exceeded");
+	assert!(p.next_component().await.unwrap().is_none());
+
+	let mut framed = FramedRead::new(input(), ContentLineCodec::new_with_options(options));
+	expect_err(framed.next().await.unwrap(), "line 2: \tlimit exceeded");
+}
+
+#[tokio::test]
+async fn encode() {
+	let mut comp = c2("House");
+	comp.add_property(p2("Heating", "gas"));
+	let (w, mut r) = tokio::io::duplex(1024);
+	let mut e = AsyncEncoder::new(w);
+	e.encode_all(&[comp, c2("Flat")]).await.unwrap();
+	drop(e);
+	let mut out = String::new();
+	r.read_to_string(&mut out).await.unwrap();
+	assert_eq!(out, "BEGIN:HOUSE\r\nHEATING:gas\r\nEND:HOUSE\r\nBEGIN:FLAT\r\nEND:FLAT\r\n");
+}
+
+#[tokio::test]
+async fn codec() {
+	let input = "BEGIN:comp\r\nFEATURE:Conten\r\n t\r\nEND:Comp\r\nBEGIN:porp\r\nEND:poRp";
+	let options = ParserOptions::lenient();
+	let got: Vec<_> = FramedRead::new(feed(input), ContentLineCodec::new_with_options(options))
+			.map(|c| c.unwrap()).collect().await;
+	assert_eq!(got.len(), 2);
+	assert_comp_equal(&got[0], &c("COMP", vec![p2("FEATURE", "Content")], vec![]));
+	assert_comp_equal(&got[1], &c2("PORP"));
+
+	let (w, mut r) = tokio::io::duplex(1024);
+	let mut sink = FramedWrite::new(w, ContentLineCodec::new());
+	sink.send(&got[0]).await.unwrap();
+	sink.send(c2("Flat")).await.unwrap();
+	drop(sink);
+	let mut out = String::new();
+	r.read_to_string(&mut out).await.unwrap();
+	assert_eq!(out, "BEGIN:COMP\r\nFEATURE:Content\r\nEND:COMP\r\nBEGIN:FLAT\r\nEND:FLAT\r\n");
+}
+
+#[test]
+fn codec_before_next_line() {
+	let mut codec = ContentLineCodec::new();
+	let mut src = BytesMut::from(&b"BEGIN:a\r\nEND:a\r\n"[..]);
+	assert!(codec.decode(&mut src).unwrap().is_none());
+	src.extend_from_slice(b"B");
+	assert_comp_equal(&codec.decode(&mut src).unwrap().unwrap(), &c2("A"));
+	assert_eq!(&src[..], b"B");
+}
+
+#[tokio::test]
+async fn codec_encoder_options() {
+	let (w, mut r) = tokio::io::duplex(1024);
+	let options = EncoderOptions { fold_width: Some(10), ..EncoderOptions::unix() };
+	let mut sink = FramedWrite::new(w, ContentLineCodec::new_with_encoder_options(ParserOptions::default(), options));
+	sink.send(c("Flat", vec![p2("FEATURE", "Content")], vec![])).await.unwrap();
+	drop(sink);
+	let mut out = String::new();
+	r.read_to_string(&mut out).await.unwrap();
+	assert_eq!(out, "BEGIN:FLAT\nFEATURE:Co\n ntent\nEND:FLAT\n");
+}
@@ -0,0 +1,104 @@
+use std::borrow::Cow;
+
+use crate::{BorrowedName, BorrowedParameters, Child, Component, ComponentSpan, Parameters, Property, PropertySpan, RawComponent, RawProperty, text};
+
+//The borrowed counterparts of Component, Property and Parameters, as produced by the BorrowedParser.
+// Names, values and parameters borrow from the input unless they had to be changed while parsing
+// (because the line was folded or a parameter value contained RFC 6868 escape sequences).
+
+#[derive(Debug)]
+pub struct BorrowedProperty<'a> {
+	pub group: Option<BorrowedName<'a>>,
+	pub name: BorrowedName<'a>,
+	pub value: Cow<'a, str>,
+	pub parameters: BorrowedParameters<'a>,
+	pub old_line: Option<(Cow<'a, str>, u32)>,
+	pub span: Option<PropertySpan>,
+	pub raw: Option<Vec<u8>>,
+}
+
+impl<'a> BorrowedProperty<'a> {
+	pub fn get_param_value(&self, name: &str) -> Option<&Vec<Cow<'a, str>>> {
+		self.parameters.get(name)
+	}
+
+	pub fn get_param_values(&self, name: &str) -> Vec<&Cow<'a, str>> {
+		self.parameters.get_all(name).collect()
+	}
+
+	//text_value returns the value with all TEXT escape sequences replaced, see `text::unescape_text`.
+	pub fn text_value(&self) -> String {
+		text::unescape_text(&self.value)
+	}
+
+	//structured_value splits the value into its components and values, see `text::split_structured`.
+	pub fn structured_value(&self) -> Vec<Vec<String>> {
+		text::split_structured(&self.value)
+	}
+
+	pub fn into_owned(self) -> Property {
+		let parameters: Parameters = self.parameters.into_iter()
+				.map(|(name, values)| (name.into_owned(), values.into_iter().map(Cow::into_owned).collect()))
+				.collect();
+		let mut property = Property {
+			group: self.group.map(BorrowedName::into_owned),
+			name: self.name.into_owned(),
+			value: self.value.into_owned(),
+			parameters,
+			old_line: self.old_line.map(|(line, num)| (line.into_owned(), num)),
+			span: self.span,
+			raw: None,
+		};
+		property.raw = self.raw.map(|lines| RawProperty::new(lines, &property));
+		property
+	}
+}
+
+#[derive(Debug)]
+pub struct BorrowedComponent<'a> {
+	pub name: BorrowedName<'a>,
+	pub properties: Vec<BorrowedProperty<'a>>,
+	pub sub_components: Vec<BorrowedComponent<'a>>,
+	pub span: Option<ComponentSpan>,
+	pub order: Vec<Child>,
+	pub raw: Option<RawComponent>,
+}
+
+impl<'a> BorrowedComponent<'a> {
+	pub fn find_property(&self, name: &str) -> Vec<&BorrowedProperty<'a>> {
+		self.properties.iter().filter(|p| p.name == name).collect()
+	}
+
+	pub fn groups(&self) -> Vec<&BorrowedName<'a>> {
+		let mut out: Vec<&BorrowedName<'a>> = Vec::new();
+		for group in self.properties.iter().filter_map(|p| p.group.as_ref()) {
+			if !out.contains(&group) {
+				out.push(group);
+			}
+		}
+		out
+	}
+
+	pub fn find_group(&self, group: &str) -> Vec<&BorrowedProperty<'a>> {
+		self.properties.iter().filter(|p| p.group.as_ref().is_some_and(|g| g == group)).collect()
+	}
+
+	pub fn find_property_in_group(&self, group: &str, name: &str) -> Vec<&BorrowedProperty<'a>> {
+		self.find_group(group).into_iter().filter(|p| p.name == name).collect()
+	}
+
+	pub fn children(&self) -> Vec<Child> {
+		crate::ordered_children(&self.order, self.properties.len(), self.sub_components.len())
+	}
+
+	pub fn into_owned(self) -> Component {
+		Component {
+			name: self.name.into_owned(),
+			properties: self.properties.into_iter().map(BorrowedProperty::into_owned).collect(),
+			sub_components: self.sub_components.into_iter().map(BorrowedComponent::into_owned).collect(),
+			span: self.span,
+			order: self.order,
+			raw: self.raw,
+		}
+	}
+}
@@ -0,0 +1,93 @@
+use std::io;
+
+use bytes::{BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::{Component, EncoderOptions, ParserOptions};
+use crate::encoder::ComponentEncode;
+use crate::parser::{Error, QueueParser};
+
+//ContentLineCodec decodes byte streams into components and encodes components, for use with
+// tokio_util's FramedRead, FramedWrite and Framed.
+pub struct ContentLineCodec {
+	parser: QueueParser,
+	encoder_options: EncoderOptions,
+}
+
+impl ContentLineCodec {
+	pub fn new() -> Self {
+		Self::new_with_options(ParserOptions::default())
+	}
+
+	pub fn new_with_options(options: ParserOptions) -> Self {
+		Self::new_with_encoder_options(options, EncoderOptions::default())
+	}
+
+	//new_with_encoder_options creates a codec which also encodes components with the given options, e.g. to
+	// write vCard 2.1 or lines ending with LF.
+	pub fn new_with_encoder_options(options: ParserOptions, encoder_options: EncoderOptions) -> Self {
+		ContentLineCodec {
+			parser: QueueParser::new(options),
+			encoder_options,
+		}
+	}
+
+	pub fn encoder_options(&self) -> &EncoderOptions {
+		&self.encoder_options
+	}
+}
+
+impl Default for ContentLineCodec {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Decoder for ContentLineCodec {
+	type Item = Component;
+	type Error = Error;
+
+	fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Component>, Error> {
+		//move all complete lines into the parser
+		while let Some(i) = src.iter().position(|&b| b == b'\n') {
+			let line = src.split_to(i + 1);
+			self.parser.push_line(line[..i].to_vec());
+		}
+		//don't wait for the end of a line which is too long anyway, the parser rejects it.
+		if self.parser.options().limits.max_line_length.is_some_and(|max| src.len() > max + 2) {
+			self.parser.push_line(src.split().to_vec());
+		}
+		//the first byte of the next line may show that the last complete line is not continued
+		self.parser.set_partial(src.first().copied());
+		self.parser.next_component()
+	}
+
+	fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Component>, Error> {
+		if let Some(c) = self.decode(src)? {
+			return Ok(Some(c));
+		}
+		//the last line doesn't need to end with '\n'
+		if !src.is_empty() {
+			self.parser.push_line(src.split().to_vec());
+		}
+		self.parser.set_partial(None);
+		self.parser.finish();
+		self.parser.next_component()
+	}
+}
+
+impl Encoder<&Component> for ContentLineCodec {
+	type Error = io::Error;
+
+	fn encode(&mut self, item: &Component, dst: &mut BytesMut) -> io::Result<()> {
+		dst.writer().encode_component_with_options(item, &self.encoder_options)
+	}
+}
+
+impl Encoder<Component> for ContentLineCodec {
+	type Error = io::Error;
+
+	fn encode(&mut self, item: Component, dst: &mut BytesMut) -> io::Result<()> {
+		self.encode(&item, dst)
+	}
+}
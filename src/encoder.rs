@@ -1,29 +1,147 @@
 #![allow(clippy::write_with_newline)]
+use std::borrow::Cow;
 use std::io::Result;
 use std::io::Write;
 
-use crate::{Component, Property};
-use crate::parser::rfc6868;
-use crate::{COMP_END_S,COMP_BEGIN_S};
+use crate::{Child, Component, Name, Property};
+use crate::parser::{rfc6868, vcard21, Dialect};
+use crate::text;
+use crate::{ALLOWED_PARAMETER_NAME_CHARS, COMP_END_S, COMP_BEGIN_S};
 
+//the maximum length of a line in octets (without the line terminator) recommended by the RFCs.
 const FOLDING_LENGTH: usize = 75;
+//the minimum fold width, so that every line can hold the fold character and a whole UTF-8 sequence.
+const MIN_FOLDING_LENGTH: usize = 5;
+
+//EncoderOptions configures how components are written.
+#[derive(Clone, Debug)]
+pub struct EncoderOptions {
+	// Write all component, property and parameter names in upper case, which is their canonical form.
+	// If false, names are written in the spelling they were parsed or created with.
+	pub uppercase_names: bool,
+	// Escape backslashes, semicolons, commas and line breaks in property values, as required for values of
+	// the type TEXT. Only use this if all values hold unescaped text, as the values are escaped once more
+	// otherwise: values set with `Property::set_text_value` are already escaped and structured values
+	// like the one of N would lose their separators.
+	pub escape_text: bool,
+	// The dialect which is written, see Dialect.
+	pub dialect: Dialect,
+	// The maximum length of a line in octets, not counting the line terminator. Longer lines are folded
+	// (or broken with soft line breaks when writing quoted-printable values). None never folds lines.
+	// Widths below 5 are treated as 5.
+	pub fold_width: Option<usize>,
+	// The character beginning the continuation of a folded line.
+	pub fold_char: FoldChar,
+	// The terminator written after every line.
+	pub line_ending: LineEnding,
+	// Write the original lines of properties and components which were parsed with `ParserOptions::lossless`
+	// and were not modified since, ignoring all other options for them. Only modified (or new) properties
+	// and components are encoded.
+	pub lossless: bool,
+}
+
+impl Default for EncoderOptions {
+	fn default() -> Self {
+		EncoderOptions {
+			uppercase_names: true,
+			escape_text: false,
+			dialect: Dialect::Standard,
+			fold_width: Some(FOLDING_LENGTH),
+			fold_char: FoldChar::Space,
+			line_ending: LineEnding::CrLf,
+			lossless: false,
+		}
+	}
+}
+
+//FoldChar is the whitespace character which continues a folded line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FoldChar {
+	#[default]
+	Space,
+	Tab,
+}
+
+impl FoldChar {
+	fn as_byte(self) -> u8 {
+		match self {
+			FoldChar::Space => b' ',
+			FoldChar::Tab => b'\t',
+		}
+	}
+}
+
+//LineEnding is the terminator of every line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineEnding {
+	//CRLF, as required by the RFCs.
+	#[default]
+	CrLf,
+	//A bare LF, for tools which expect Unix line endings. Such files can only be read with lenient line endings.
+	Lf,
+}
+
+impl LineEnding {
+	pub fn as_str(self) -> &'static str {
+		match self {
+			LineEnding::CrLf => "\r\n",
+			LineEnding::Lf => "\n",
+		}
+	}
+}
+
+impl EncoderOptions {
+	//preserve_case returns options which write all names in their original spelling.
+	pub fn preserve_case() -> Self {
+		EncoderOptions { uppercase_names: false, ..Self::default() }
+	}
+
+	//vcard21 returns options which write vCard 2.1.
+	pub fn vcard21() -> Self {
+		EncoderOptions { dialect: Dialect::VCard21, ..Self::default() }
+	}
+
+	//lossless returns options which write unmodified lines as they were parsed.
+	pub fn lossless() -> Self {
+		EncoderOptions { lossless: true, ..Self::default() }
+	}
+
+	//unix returns options which terminate lines with LF.
+	pub fn unix() -> Self {
+		EncoderOptions { line_ending: LineEnding::Lf, ..Self::default() }
+	}
+
+	//width returns the width lines are folded at, or None if lines are not folded.
+	fn width(&self) -> Option<usize> {
+		self.fold_width.map(|width| width.max(MIN_FOLDING_LENGTH))
+	}
+}
 
 pub struct Encoder<W: Write> {
-	out: W
+	out: W,
+	options: EncoderOptions,
 }
 
 impl<W> Encoder<W> where W: Write {
 	pub fn new(writer: W) -> Encoder<W> {
-		Encoder { out: writer }
+		Self::new_with_options(writer, EncoderOptions::default())
+	}
+
+	pub fn new_with_options(writer: W, options: EncoderOptions) -> Encoder<W> {
+		Encoder { out: writer, options }
+	}
+
+	pub fn options(&self) -> &EncoderOptions {
+		&self.options
 	}
 
 	pub fn encode(&mut self, component: &Component) -> Result<()> {
-		self.out.encode_component(component)
+		self.out.encode_component_with_options(component, &self.options)
 	}
 
 	pub fn encode_all(&mut self, component: &[Component]) -> Result<()> {
 		for c in component {
-			self.out.encode_component(c)?
+			self.encode(c)?
 		}
 		Ok(())
 	}
@@ -34,62 +152,176 @@ impl<W> Encoder<W> where W: Write {
 }
 
 pub trait ComponentEncode {
-	fn encode_component(&mut self, component: &Component) -> Result<()>;
+	fn encode_component(&mut self, component: &Component) -> Result<()> {
+		self.encode_component_with_options(component, &EncoderOptions::default())
+	}
+
+	fn encode_component_with_options(&mut self, component: &Component, options: &EncoderOptions) -> Result<()>;
 }
 
 impl<W> ComponentEncode for W where W: Write {
-	fn encode_component(&mut self, component: &Component) -> Result<()> {
-		write!(self, "{}:{}\r\n", COMP_BEGIN_S, component.name.to_uppercase())?;
+	fn encode_component_with_options(&mut self, component: &Component, options: &EncoderOptions) -> Result<()> {
+		let raw = component.raw.as_ref().filter(|raw| options.lossless && raw.is_unmodified(component.name.as_str()));
+		match raw {
+			Some(raw) => write_raw(self, &raw.begin, options)?,
+			None => write!(self, "{}:{}{}", COMP_BEGIN_S, name(&component.name, options), options.line_ending.as_str())?,
+		}
 
-		for prop in &component.properties {
-			encode_property(self, prop)?;
+		for child in component.children() {
+			match child {
+				Child::Property(i) => encode_property(self, &component.properties[i], options)?,
+				Child::SubComponent(i) => self.encode_component_with_options(&component.sub_components[i], options)?,
+			}
 		}
 
-		for comp in &component.sub_components {
-			self.encode_component(comp)?;
+		match raw.and_then(|raw| raw.end.as_ref()) {
+			Some(end) => write_raw(self, end, options),
+			None => write!(self, "{}:{}{}", COMP_END_S, name(&component.name, options), options.line_ending.as_str()),
 		}
+	}
+}
 
-		write!(self, "{}:{}\r\n", COMP_END_S, component.name.to_uppercase())?;
-		Ok(())
+//name returns the name as it should be written according to the options.
+fn name<'a>(name: &'a Name, options: &EncoderOptions) -> Cow<'a, str> {
+	if options.uppercase_names {
+		Cow::Owned(name.to_uppercase())
+	} else {
+		Cow::Borrowed(name.as_str())
 	}
 }
 
-fn encode_property<W: Write>(writer: &mut W, property: &Property) -> Result<()> {
+fn encode_property<W: Write>(writer: &mut W, property: &Property, options: &EncoderOptions) -> Result<()> {
+	if let Some(raw) = property.raw.as_ref().filter(|raw| options.lossless && raw.is_unmodified(property)) {
+		return write_raw(writer, &raw.lines, options);
+	}
 	let mut buf = vec![];
 
-	write_folded(writer, &mut buf, &property.name.to_uppercase())?;
+	if let Some(group) = &property.group {
+		write_folded(writer, &mut buf, &name(group, options), options)?;
+		write_folded(writer, &mut buf, ".", options)?;
+	}
+	write_folded(writer, &mut buf, &name(&property.name, options), options)?;
 
+	let vcard21 = options.dialect == Dialect::VCard21;
+	//parameters are written in their order, every entry (including repeated ones) as "NAME=value1,value2".
+	// In vCard 2.1, the values of TYPE are written as bare parameters if possible.
 	for (key, values) in &property.parameters {
-		write_folded(writer, &mut buf, ";")?;
-		write_folded(writer, &mut buf, key.to_uppercase().as_str())?;
-		write_folded(writer, &mut buf, "=")?;
+		if vcard21 && key.as_str().eq_ignore_ascii_case("TYPE") && values.iter().all(|v| is_bare(v)) {
+			for val in values {
+				write_folded(writer, &mut buf, ";", options)?;
+				write_folded(writer, &mut buf, val, options)?;
+			}
+			continue;
+		}
+		write_folded(writer, &mut buf, ";", options)?;
+		write_folded(writer, &mut buf, &name(key, options), options)?;
+		write_folded(writer, &mut buf, "=", options)?;
 
 		for (i, val) in values.iter().enumerate() {
-			if i > 0 { write_folded(writer, &mut buf, ",")?; }
+			if i > 0 { write_folded(writer, &mut buf, ",", options)?; }
 
 			let escaped = rfc6868::escape_param_value(val);
 
 			if escaped.contains(',') || escaped.contains(';') || escaped.contains(':') {
-				write_folded(writer, &mut buf, "\"")?;
-				write_folded(writer, &mut buf, &escaped)?;
-				write_folded(writer, &mut buf, "\"")?;
+				write_folded(writer, &mut buf, "\"", options)?;
+				write_folded(writer, &mut buf, &escaped, options)?;
+				write_folded(writer, &mut buf, "\"", options)?;
 			} else {
-				write_folded(writer, &mut buf, &escaped)?;
+				write_folded(writer, &mut buf, &escaped, options)?;
 			}
 		}
 	}
 
 
-	write_folded(writer, &mut buf, ":")?;
-	write_folded(writer, &mut buf, &property.value)?;
+	let value = if options.escape_text {
+		Cow::Owned(text::escape_text(&property.value))
+	} else {
+		Cow::Borrowed(property.value.as_str())
+	};
+	//vCard 2.1 can not fold values containing line breaks or non-ASCII characters, so they are encoded as
+	// quoted-printable unless the property declares an encoding already.
+	if vcard21 && vcard21::needs_quoted_printable(&value) && !property.parameters.contains_key("ENCODING") {
+		write_folded(writer, &mut buf, ";ENCODING=QUOTED-PRINTABLE", options)?;
+		if !value.is_ascii() {
+			write_folded(writer, &mut buf, ";CHARSET=UTF-8", options)?;
+		}
+		write_folded(writer, &mut buf, ":", options)?;
+		write_quoted_printable(writer, &mut buf, &value, options)?;
+	} else {
+		write_folded(writer, &mut buf, ":", options)?;
+		write_folded(writer, &mut buf, &value, options)?;
+	}
 	writer.write_all(buf.as_slice())?;
-	write!(writer, "\r\n")
+	writer.write_all(options.line_ending.as_str().as_bytes())
+}
+
+//write_raw writes the lines as they were parsed. Only the last line of the input may lack its line ending,
+// which is added then.
+fn write_raw<W: Write>(writer: &mut W, lines: &[u8], options: &EncoderOptions) -> Result<()> {
+	writer.write_all(lines)?;
+	if !lines.ends_with(b"\n") {
+		writer.write_all(options.line_ending.as_str().as_bytes())?;
+	}
+	Ok(())
+}
+
+//is_bare returns whether the parameter value can be written without its name in vCard 2.1. Bare encodings
+// are parameters of ENCODING, so a TYPE with such a value keeps its name.
+fn is_bare(value: &str) -> bool {
+	!value.is_empty() && value.chars().all(|c| ALLOWED_PARAMETER_NAME_CHARS.contains(c))
+			&& !vcard21::ENCODINGS.iter().any(|e| e.eq_ignore_ascii_case(value))
 }
 
-fn write_folded<W: Write>(writer: &mut W, buf: &mut Vec<u8>, mut data: &str) -> Result<()> {
-	while buf.len() + data.len() > FOLDING_LENGTH {
+//write_quoted_printable writes the value encoded as quoted-printable, breaking the lines with soft line
+// breaks ('=' at the end of the line) instead of folding them. The buffer has to end with the ':' before the value.
+fn write_quoted_printable<W: Write>(writer: &mut W, buf: &mut Vec<u8>, value: &str, options: &EncoderOptions) -> Result<()> {
+	let encoded = vcard21::encode_quoted_printable(value);
+	let width = match options.width() {
+		Some(width) => width,
+		None => {
+			buf.extend(encoded.as_bytes());
+			return Ok(());
+		}
+	};
+	let mut rest = encoded.as_str();
+	while !rest.is_empty() {
+		//an encoded byte ("=XX") must not be split
+		let len = if rest.starts_with('=') { 3 } else { 1 };
+		//leave room for the '=' of the soft line break, unless this is the last byte
+		let needed = if rest.len() > len { len + 1 } else { len };
+		//if the name and parameters fill the line, the ':' is folded to the next line instead of breaking
+		// the line before the value
+		if rest.len() == encoded.len() && buf.len() + needed > width && buf.len() > 2 {
+			let colon = buf.pop();
+			writer.write_all(buf.as_slice())?;
+			writer.write_all(options.line_ending.as_str().as_bytes())?;
+			buf.clear();
+			buf.push(options.fold_char.as_byte());
+			buf.extend(colon);
+		}
+		if buf.len() + needed > width {
+			writer.write_all(buf.as_slice())?;
+			writer.write_all(b"=")?;
+			writer.write_all(options.line_ending.as_str().as_bytes())?;
+			buf.clear();
+		}
+		buf.extend(&rest.as_bytes()[..len]);
+		rest = &rest[len..];
+	}
+	Ok(())
+}
+
+fn write_folded<W: Write>(writer: &mut W, buf: &mut Vec<u8>, mut data: &str, options: &EncoderOptions) -> Result<()> {
+	let width = match options.width() {
+		Some(width) => width,
+		None => {
+			buf.extend(data.as_bytes());
+			return Ok(());
+		}
+	};
+	while buf.len() + data.len() > width {
 		//dlen bytes of data can fit into the current line.
-		let mut dlen = FOLDING_LENGTH - buf.len();
+		let mut dlen = width - buf.len();
 
 
 		//make sure not to break in the middle of utf8-sequences (not required by the standard)
@@ -98,12 +330,12 @@ fn write_folded<W: Write>(writer: &mut W, buf: &mut Vec<u8>, mut data: &str) ->
 
 		//write out the buffer, write out the allowed count of bytes and a newline character.
 		writer.write_all(buf.as_slice())?;
-		writer.write_all(data[..dlen].as_bytes())?;
-		writer.write_all(b"\r\n")?;
+		writer.write_all(&data.as_bytes()[..dlen])?;
+		writer.write_all(options.line_ending.as_str().as_bytes())?;
 
-		//push ' ' into the empty buffer, to begin a new folded line
+		//push the fold character into the empty buffer, to begin a new folded line
 		buf.clear();
-		buf.push(b' ');
+		buf.push(options.fold_char.as_byte());
 		//set data to include only non-written data
 		data = &data[dlen..];
 	}
@@ -1,4 +1,7 @@
-use crate::test_helper::{c2, p2};
+use std::io::Cursor;
+
+use crate::{BorrowedParser, Child, Component, Encoder, EncoderOptions, FoldChar, LineEnding, Parser, ParserOptions};
+use crate::test_helper::{assert_comp_equal, c2, p, p2, pm};
 
 #[test]
 fn simple() {
@@ -37,14 +40,7 @@ fn property_complex() {
 			"COMMENT=\"This is a very long \r\n comment,more than 2^^3 monkeys hat to sit 20 hours to write this ^n thing \r\n with linebreaks.\":" +
 			"electric\r\n" +
 			"END:HOUSE\r\n";
-	let alternative = "BEGIN:HOUSE\r\n".to_string() +
-			"HEATING;" +
-			"COMMENT=\"This is a very long comment,more than 2^^3 monkeys hat to \r\n sit 20 hours to write this ^n thing with linebreaks.\";" +
-			"VENDOR=YourGas Co^',\r\n \"City:Energy LLC\":" +
-			"electric\r\n" +
-			"END:HOUSE\r\n";
-	let s=c.encode_to_string();
-	assert!(s == expected || s == alternative, "\nGot:     {:?}\nExpected:{:?}\nOr      :{:?}",s,expected,alternative);
+	assert_eq!(c.encode_to_string(), expected);
 }
 
 #[test]
@@ -85,35 +81,207 @@ fn nested_complex() {
 			"VENDOR=YourGas Co^',\"City:Energy LLC\";"+
 			"COMMENT=\"This is a very long\r\n  comment,more than 2^^3 monkeys hat to sit 20 hours to write this ^n thing\r\n  with linebreaks.\":electric2\r\n"+
 			"END:FLAT\r\nEND:HOUSE\r\n";
-	let alt1="BEGIN:HOUSE\r\n".to_string() +
-			"HEATING;"+
-			"COMMENT=\"This is a very long comment,more than 2^^3 monkeys hat to \r\n sit 20 hours to write this ^n thing with linebreaks.\";" +
-			"VENDOR=YourGas Co^',\r\n \"City:Energy LLC\":electric\r\n" +
-			"BEGIN:FLAT\r\n"+
-			"HEATING2;"+
-			"VENDOR=YourGas Co^',\"City:Energy LLC\";"+
-			"COMMENT=\"This is a very long\r\n  comment,more than 2^^3 monkeys hat to sit 20 hours to write this ^n thing\r\n  with linebreaks.\":electric2\r\n"+
-			"END:FLAT\r\nEND:HOUSE\r\n";
-	let alt2="BEGIN:HOUSE\r\n".to_string() +
-			"HEATING;"+
-			"VENDOR=YourGas Co^',\"City:Energy LLC\";"+
-			"COMMENT=\"This is a very long \r\n comment,more than 2^^3 monkeys hat to sit 20 hours to write this ^n thing \r\n with linebreaks.\":electric\r\n"+
-			"BEGIN:FLAT\r\n"+
-			"HEATING2;"+
-			"COMMENT=\"This is a very long comment,more than 2^^3 monkeys hat to\r\n  sit 20 hours to write this ^n thing with linebreaks.\";" +
-			"VENDOR=YourGas Co^'\r\n ,\"City:Energy LLC\":electric2\r\n" +
-			"END:FLAT\r\nEND:HOUSE\r\n";
-	let alt3="BEGIN:HOUSE\r\n".to_string() +
-			"HEATING;"+
-			"COMMENT=\"This is a very long comment,more than 2^^3 monkeys hat to \r\n sit 20 hours to write this ^n thing with linebreaks.\";" +
-			"VENDOR=YourGas Co^',\r\n \"City:Energy LLC\":electric\r\n" +
-			"BEGIN:FLAT\r\n"+
-			"HEATING2;"+
-			"COMMENT=\"This is a very long comment,more than 2^^3 monkeys hat to\r\n  sit 20 hours to write this ^n thing with linebreaks.\";" +
-			"VENDOR=YourGas Co^'\r\n ,\"City:Energy LLC\":electric2\r\n" +
-			"END:FLAT\r\nEND:HOUSE\r\n";
+	assert_eq!(c.encode_to_string(), expected);
+}
+
+#[test]
+fn parameter_order() {
+	let mut c = c2("Card");
+	c.add_property(p("Tel", "123", pm(vec![("Z", vec!["1"]), ("TYPE", vec!["a", "b"]), ("A", vec!["2"])])));
+	c.add_property(p("Tel", "456", pm(vec![("TYPE", vec!["a"]), ("X", vec!["3"]), ("TYPE", vec!["b"])])));
+	let expected = "BEGIN:CARD\r\nTEL;Z=1;TYPE=a,b;A=2:123\r\nTEL;TYPE=a;X=3;TYPE=b:456\r\nEND:CARD\r\n";
+	assert_eq!(c.encode_to_string(), expected);
+}
+
+#[test]
+fn preserve_case() {
+	let input = "BEGIN:vCard\r\nX-MyApp-Flag;Type=Home:on\r\nEND:VCARD\r\n";
+	let c = Parser::new(Cursor::new(input)).next_component().unwrap().unwrap();
+	assert_eq!(c.name.as_str(), "vCard");
+	assert_eq!(c.find_property("x-myapp-flag")[0].name.as_str(), "X-MyApp-Flag");
+	assert_eq!(c.properties[0].get_param_value("TYPE").unwrap(), &vec!["Home"]);
+
+	assert_eq!(c.encode_to_string(), "BEGIN:VCARD\r\nX-MYAPP-FLAG;TYPE=Home:on\r\nEND:VCARD\r\n");
+
+	let mut buf = vec![];
+	Encoder::new_with_options(&mut buf, EncoderOptions::preserve_case()).encode(&c).unwrap();
+	//the END line is written with the spelling of the BEGIN line
+	assert_eq!(String::from_utf8(buf).unwrap(), "BEGIN:vCard\r\nX-MyApp-Flag;Type=Home:on\r\nEND:vCard\r\n");
+}
+
+#[test]
+fn group() {
+	let input = "BEGIN:VCARD\r\nitem1.EMAIL;TYPE=INTERNET:a@b.c\r\nitem1.X-ABLabel:work\r\nEND:VCARD\r\n";
+	let c = Parser::new(Cursor::new(input)).next_component().unwrap().unwrap();
+	assert_eq!(c.encode_to_string(), "BEGIN:VCARD\r\nITEM1.EMAIL;TYPE=INTERNET:a@b.c\r\nITEM1.X-ABLABEL:work\r\nEND:VCARD\r\n");
+
+	let mut buf = vec![];
+	Encoder::new_with_options(&mut buf, EncoderOptions::preserve_case()).encode(&c).unwrap();
+	assert_eq!(String::from_utf8(buf).unwrap(), input);
+}
+
+#[test]
+fn escape_text() {
+	let mut c = c2("VEVENT");
+	c.add_property(p2("SUMMARY", "Meeting; room 1, 2\nbring \\ snacks"));
+	assert_eq!(c.encode_to_string(), "BEGIN:VEVENT\r\nSUMMARY:Meeting; room 1, 2\nbring \\ snacks\r\nEND:VEVENT\r\n");
+
+	let mut buf = vec![];
+	let options = EncoderOptions { escape_text: true, ..EncoderOptions::default() };
+	Encoder::new_with_options(&mut buf, options).encode(&c).unwrap();
+	assert_eq!(String::from_utf8(buf).unwrap(), "BEGIN:VEVENT\r\nSUMMARY:Meeting\\; room 1\\, 2\\nbring \\\\ snacks\r\nEND:VEVENT\r\n");
+}
+
+#[test]
+fn vcard21() {
+	let mut c = c2("VCARD");
+	c.add_property(p("TEL", "123", pm(vec![("TYPE", vec!["home", "voice"]), ("TYPE", vec!["x y"])])));
+	c.add_property(p2("NOTE", "Grüße\r\n".repeat(8).as_str()));
+	c.add_property(p("PHOTO", "ä", pm(vec![("ENCODING", vec!["8BIT"])])));
+	let mut out = vec![];
+	Encoder::new_with_options(&mut out, EncoderOptions::vcard21()).encode(&c).unwrap();
+	let encoded = String::from_utf8(out).unwrap();
+	assert_eq!(encoded, "BEGIN:VCARD\r\n".to_string() +
+			"TEL;home;voice;TYPE=x y:123\r\n" +
+			"NOTE;ENCODING=QUOTED-PRINTABLE;CHARSET=UTF-8:Gr=C3=BC=C3=9Fe=0D=0AGr=C3=BC=\r\n" +
+			"=C3=9Fe=0D=0AGr=C3=BC=C3=9Fe=0D=0AGr=C3=BC=C3=9Fe=0D=0AGr=C3=BC=C3=9Fe=0D=\r\n" +
+			"=0AGr=C3=BC=C3=9Fe=0D=0AGr=C3=BC=C3=9Fe=0D=0AGr=C3=BC=C3=9Fe=0D=0A\r\n" +
+			"PHOTO;ENCODING=8BIT:ä\r\n" +
+			"END:VCARD\r\n");
+
+	//the parser decodes what the encoder wrote
+	let mut parser = Parser::new_with_options(Cursor::new(encoded), crate::ParserOptions::vcard21());
+	let parsed = parser.next_component().unwrap().unwrap();
+	assert_eq!(parsed.properties[1].value, "Grüße\r\n".repeat(8));
+	assert_eq!(parsed.properties[1].parameters.len(), 0);
+}
+
+#[test]
+fn vcard21_type_encodings() {
+	//TYPE values which read like encodings are not written bare, they would be parsed as ENCODING
+	let mut c = c2("VCARD");
+	c.add_property(p("X-A", "=41", pm(vec![("TYPE", vec!["quoted-printable", "home"])])));
+	c.add_property(p("X-B", "1", pm(vec![("TYPE", vec!["8BIT"]), ("TYPE", vec!["BASE64"])])));
+	let encoded = c.encode_to_string_with_options(&EncoderOptions::vcard21());
+	assert_eq!(encoded, "BEGIN:VCARD\r\nX-A;TYPE=quoted-printable,home:=41\r\nX-B;TYPE=8BIT;TYPE=BASE64:1\r\nEND:VCARD\r\n");
+	let parsed = Parser::new_with_options(Cursor::new(&encoded), ParserOptions::vcard21()).next_component().unwrap().unwrap();
+	assert_comp_equal(&parsed, &c);
+	assert_eq!(parsed.properties[0].value, "=41");
+}
+
+#[test]
+fn folding_options() {
+	let mut c = c2("VEVENT");
+	c.add_property(p2("DESCRIPTION", "0123456789".repeat(3).as_str()));
+
+	let options = EncoderOptions { fold_width: Some(20), fold_char: FoldChar::Tab, ..EncoderOptions::default() };
+	assert_eq!(c.encode_to_string_with_options(&options), "BEGIN:VEVENT\r\n".to_string() +
+			"DESCRIPTION:01234567\r\n" +
+			"\t8901234567890123456\r\n" +
+			"\t789\r\n" +
+			"END:VEVENT\r\n");
+
+	let options = EncoderOptions { fold_width: None, ..EncoderOptions::unix() };
+	c.add_property(p2("SUMMARY", "x".repeat(100).as_str()));
+	let encoded = c.encode_to_string_with_options(&options);
+	assert_eq!(encoded, format!("BEGIN:VEVENT\nDESCRIPTION:{}\nSUMMARY:{}\nEND:VEVENT\n", "0123456789".repeat(3), "x".repeat(100)));
+	let parsed = Parser::new_with_options(Cursor::new(encoded), ParserOptions::lenient()).next_component().unwrap().unwrap();
+	assert_comp_equal(&parsed, &c);
+
+	//a width too small for the fold character and a multi-byte character
+	let mut c = c2("A");
+	c.add_property(p2("B", "\u{1F600}\u{1F600}"));
+	let options = EncoderOptions { fold_width: Some(1), ..EncoderOptions::default() };
+	assert_eq!(c.encode_to_string_with_options(&options), "BEGIN:A\r\nB:\r\n \u{1F600}\r\n \u{1F600}\r\nEND:A\r\n");
+}
+
+#[test]
+fn quoted_printable_line_endings() {
+	let mut c = c2("VCARD");
+	c.add_property(p2("NOTE", "äöü"));
+	let options = EncoderOptions { fold_width: Some(50), line_ending: LineEnding::Lf, ..EncoderOptions::vcard21() };
+	assert_eq!(c.encode_to_string_with_options(&options), "BEGIN:VCARD\n".to_string() +
+			"NOTE;ENCODING=QUOTED-PRINTABLE;CHARSET=UTF-8:=C3=\n" +
+			"=A4=C3=B6=C3=BC\n" +
+			"END:VCARD\n");
+}
+
+#[test]
+fn quoted_printable_width() {
+	let value = "Grüße aus Köln\r\n".repeat(3);
+	for width in 5..=60 {
+		let mut c = c2("VCARD");
+		c.add_property(p("NOTE", &value, pm(vec![("X-LONG-PARAMETER", vec!["abcdefghijklmnopqrstuvwxyz"])])));
+		c.add_property(p2("X", "ä"));
+		let options = EncoderOptions { fold_width: Some(width), ..EncoderOptions::vcard21() };
+		let encoded = c.encode_to_string_with_options(&options);
+		//BEGIN and END lines are not folded
+		for line in encoded.split_terminator("\r\n").filter(|l| !l.ends_with(":VCARD")) {
+			assert!(line.len() <= width, "line {:?} is longer than {}", line, width);
+			//only at the minimum width, a line may end before the value
+			assert!(width == 5 || !line.ends_with(":="), "soft line break before the value in {:?}", line);
+		}
+		let parsed = Parser::new_with_options(Cursor::new(&encoded), ParserOptions::vcard21()).next_component().unwrap().unwrap();
+		assert_eq!(parsed.properties[0].value, value, "{}", encoded);
+		assert_eq!(parsed.properties[1].value, "ä", "{}", encoded);
+	}
+}
+
+#[test]
+fn interleaved_order() {
+	let input = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VTIMEZONE\r\nTZID:a\r\nEND:VTIMEZONE\r\nPRODID:x\r\nBEGIN:VEVENT\r\nEND:VEVENT\r\nX-A:1\r\nEND:VCALENDAR\r\n";
+	let c = Parser::new(Cursor::new(input)).next_component().unwrap().unwrap();
+	assert_eq!(c.children(), vec![Child::Property(0), Child::SubComponent(0), Child::Property(1), Child::SubComponent(1), Child::Property(2)]);
+	assert_eq!(c.encode_to_string(), input);
+	let borrowed = BorrowedParser::new(input.as_bytes()).next_component().unwrap().unwrap();
+	assert_eq!(borrowed.children(), c.children());
+	assert_eq!(borrowed.into_owned().encode_to_string(), input);
+
+	//children which are not in the order are written last
+	let mut c = c;
+	c.properties.push(p2("X-B", "2"));
+	c.order.retain(|&child| child != Child::Property(1));
+	c.order.push(Child::SubComponent(7));
+	assert_eq!(c.encode_to_string(), "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VTIMEZONE\r\nTZID:a\r\nEND:VTIMEZONE\r\nBEGIN:VEVENT\r\nEND:VEVENT\r\nX-A:1\r\nPRODID:x\r\nX-B:2\r\nEND:VCALENDAR\r\n");
+
+	let mut c = Component::new_empty("A".to_string()).unwrap();
+	c.add_sub_component(c2("B"));
+	c.add_property(p2("C", "1"));
+	assert_eq!(c.encode_to_string(), "BEGIN:A\r\nBEGIN:B\r\nEND:B\r\nC:1\r\nEND:A\r\n");
+}
+
+#[test]
+fn lossless() {
+	let input = "\u{feff}begin:vcard\r\nVERSION:4.0\r\nfn;Language=\"de\":Max Mus\r\n\ttermann\r\nBEGIN:x\r\nEND:X\r\nNOTE:a\r\n very long note\r\nend:VCARD\r\n";
+	let options = ParserOptions { lossless: true, ..ParserOptions::default() };
+	let mut c = Parser::new_with_options(Cursor::new(input), options.clone()).next_component().unwrap().unwrap();
+	assert_eq!(c.properties[1].raw.as_ref().unwrap().lines, b"fn;Language=\"de\":Max Mus\r\n\ttermann\r\n");
+	assert_eq!(c.encode_to_string_with_options(&EncoderOptions::lossless()), input);
+	let borrowed = BorrowedParser::new_with_options(input, options).next_component().unwrap().unwrap();
+	assert_eq!(borrowed.into_owned().encode_to_string_with_options(&EncoderOptions::lossless()), input);
+
+	//only the modified lines are encoded again
+	c.properties[2].value.push('!');
+	c.sub_components[0].name = "Y".into();
+	c.add_property(p2("X-NEW", "1"));
+	assert_eq!(c.encode_to_string_with_options(&EncoderOptions::lossless()), "\u{feff}begin:vcard\r\nVERSION:4.0\r\nfn;Language=\"de\":Max Mus\r\n\ttermann\r\n".to_string() +
+			"BEGIN:Y\r\nEND:Y\r\nNOTE:avery long note!\r\nX-NEW:1\r\nend:VCARD\r\n");
+	//without the option, all lines are encoded
+	assert_eq!(c.encode_to_string(), "BEGIN:VCARD\r\nVERSION:4.0\r\nFN;LANGUAGE=de:Max Mustermann\r\nBEGIN:Y\r\nEND:Y\r\nNOTE:avery long note!\r\nX-NEW:1\r\nEND:VCARD\r\n");
+}
+
+#[test]
+fn lossless_lenient() {
+	let input = b"BEGIN:VCARD\n\nVERSION:2.1\r\nN;CHARSET=ISO-8859-1:M\xfcller\nNOTE;QUOTED-PRINTABLE:a=\nb\nEND:VCARD";
+	let options = ParserOptions { lossless: true, ..ParserOptions::vcard21() };
+	let mut c = Parser::new_with_options(Cursor::new(&input[..]), options).next_component().unwrap().unwrap();
+	assert_eq!(c.properties[1].value, "Müller");
+	let mut out = vec![];
+	Encoder::new_with_options(&mut out, EncoderOptions::lossless()).encode(&c).unwrap();
+	//the line ending of the last line is added
+	assert_eq!(out, [&input[..], b"\r\n"].concat());
 
-	let s=c.encode_to_string();
-	assert!(s == expected || s == alt1 || s == alt2 || s == alt3,
-			"\nGot:     {:?}\nExpected:{:?}\nOr      :{:?}\nOr      :{:?}\nOr      :{:?}",s,expected,alt1,alt2,alt3);
+	c.properties[1].parameters.insert("X", vec!["1".to_string()]);
+	let options = EncoderOptions { lossless: true, ..EncoderOptions::vcard21() };
+	assert_eq!(c.encode_to_string_with_options(&options), "BEGIN:VCARD\n\nVERSION:2.1\r\nN;X=1;ENCODING=QUOTED-PRINTABLE;CHARSET=UTF-8:M=C3=BCller\r\nNOTE;QUOTED-PRINTABLE:a=\nb\nEND:VCARD\r\n");
 }
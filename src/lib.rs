@@ -26,7 +26,10 @@
 //! ```
 //!
 //! More on this can be read in the mentioned RFCs and also [RFC 6868], which defines the method for
-//! escaping parameter values.
+//! escaping parameter values. Property values of the type TEXT are escaped with backslashes, which
+//! the [`text`] module (and `Property::text_value`/`Property::set_text_value`) takes care of.
+//! Structured values like the one of N (`Public;John;;Dr.`) and lists like the one of CATEGORIES
+//! (`a,b,c`) are split by `Property::structured_value` and built by `Property::set_structured_value`.
 //!
 //! ## Encoding/Parsing
 //! If you want to decode your Contentline-String, you just have to create a Parser from something
@@ -45,18 +48,97 @@
 //! ```
 //!
 //! The parser can also read from files and network sources in the same manner, as long as they implement `Read`.
+//! If the input arrives in chunks instead (e.g. from a message queue), the chunks can be fed into a
+//! [`PushParser`], which returns the components as soon as all of their lines were received.
+//! Tools which have to see every character of the input, like formatters and syntax highlighters, can
+//! use the concrete syntax tree built by `Cst::parse` instead, which contains all tokens with their spans.
+//! The tokens can also be read one by one from a `Lexer`, which reports the physical lines of folded tokens.
+//! With the `rayon` feature enabled, `parse_parallel` parses the top-level components of a byte slice
+//! (e.g. a memory-mapped file) on multiple threads.
 //!
+//! By default, the parser strictly requires every line to end with CRLF. Many tools write files with
+//! bare LF line endings instead, these can be read by creating the parser with [`ParserOptions::lenient`]:
+//!
+//! ```
+//! use std::io::Cursor;
+//! use contentline::{Parser, ParserOptions};
+//! let input = "BEGIN:comp\nmyprop:none\nEND:comp";
+//! let mut parser=Parser::new_with_options(Cursor::new(input), ParserOptions::lenient());
+//! let component = parser.next_component().unwrap();
+//! ```
+//!
+//!
+//! Lines which are not valid UTF-8 are rejected unless `ParserOptions::decoding` allows to replace the
+//! invalid sequences or to decode them with another character set (like Windows-1252), see [`Decoding`].
+//!
+//! Legacy vCard 2.1 files (with bare parameters like `TEL;HOME;VOICE`, quoted-printable values and
+//! other character sets than UTF-8) can be read with [`ParserOptions::vcard21`] and written with
+//! `EncoderOptions::vcard21`, see [`Dialect`].
+//!
+//! When parsing untrusted input, the resources used by the parser should be restricted with [`Limits`]
+//! (`ParserOptions::limits`). Exceeding a limit stops the parser with an `Error::LimitExceeded`.
+//!
+//! Errors can be inspected with their accessor methods (`line`, `column`, `offending_text`, `expected`, ...).
+//! [`Error::kind`] tells what went wrong as an [`ErrorKind`], whose `code` is a stable identifier, and
+//! `Error::path` the components which were open, like `VCALENDAR[0]/VEVENT[41]/VALARM[0]` (see [`ComponentPath`]).
+//!
+//! With the `fancy-errors` feature enabled, `Error::report` renders an error together with the lines
+//! of the input it was found in, similar to the error messages of compilers. Errors of a parser with another
+//! dialect are rendered with `Error::report_with_options`, so that the lines are unfolded the same way.
+//!
+//! To report all problems of an input at once (e.g. in a validator or an editor), set
+//! `ParserOptions::collect_diagnostics`. The parser then skips or repairs broken lines instead of failing
+//! and records a [`Diagnostic`] with a [`Span`] for each of them, which can be fetched with `take_diagnostics`:
+//!
+//! ```
+//! use contentline::{BorrowedParser, ParserOptions};
+//! let input = "BEGIN:comp\r\nmyprop;:none\r\nEND:comp\r\n";
+//! let options = ParserOptions { collect_diagnostics: true, ..ParserOptions::default() };
+//! let mut parser = BorrowedParser::new_with_options(input, options);
+//! let component = parser.next_component().unwrap().unwrap();
+//! assert!(component.properties.is_empty());
+//! assert_eq!(parser.take_diagnostics()[0].span.start_line, 2);
+//! ```
+//!
+//! If the whole input is already in memory, a [`BorrowedParser`] can be used instead. It produces
+//! [`BorrowedComponent`]s whose names, values and parameters borrow from the input and are only copied
+//! if they had to be changed, e.g. because a line was folded:
+//!
+//! ```
+//! let input = "BEGIN:comp\r\nmyprop:none\r\nEND:comp\r\n";
+//! let mut parser=contentline::BorrowedParser::new(input);
+//! let component = parser.next_component().unwrap().unwrap();
+//! assert_eq!(component.properties[0].value, "none");
+//! let owned:contentline::Component = component.into_owned();
+//! ```
+//!
+//! Instead of whole components, an [`EventReader`] returns one [`Event`] per line, so that even huge
+//! inputs can be processed without keeping them in memory:
+//!
+//! ```
+//! use std::io::Cursor;
+//! use contentline::{Event, EventReader};
+//! let input = "BEGIN:comp\r\nmyprop:none\r\nEND:comp\r\n";
+//! for event in EventReader::new(Cursor::new(input)) {
+//!     if let Event::Property(p) = event.unwrap() {
+//!         assert_eq!(p.value, "none");
+//!     }
+//! }
+//! ```
+//!
+//! Parsed components and properties carry a [`Span`] for every part of them (`span` field), which
+//! tells where in the input (byte offsets and physical lines, before unfolding) they were found.
+//!
+//! With the `async` feature enabled, components can also be read from tokio's `AsyncBufRead` with an
+//! [`AsyncParser`] (which is also a `Stream`) and written to an `AsyncWrite` with an [`AsyncEncoder`].
+//! The [`ContentLineCodec`] allows using `tokio_util`'s `FramedRead`/`FramedWrite` instead.
 //!
 //! To encode an existing Component struct, you just have to have something implementing `Write` ready.
 //!
 //!
 //! ```
 //! use contentline::{Component, Encoder};
-//! let input = Component{
-//!     name:"comp".to_string(),
-//!     properties:vec![],
-//!     sub_components:vec![]
-//! };
+//! let input = Component::new_empty("comp".to_string()).unwrap();
 //!
 //! let output:Vec<u8> = vec![];
 //!
@@ -66,6 +148,14 @@
 //!
 //! ```
 //!
+//! Names of components, properties and parameters are stored in the spelling of the input, but compare
+//! case-insensitively (see [`Name`]). The encoder writes them in upper case, unless it was created with
+//! [`EncoderOptions::preserve_case`]. The other [`EncoderOptions`] control how lines are folded and
+//! terminated, e.g. `EncoderOptions::unix` writes LF line endings. Properties and sub-components are
+//! written in the order they were parsed in, which is recorded in `Component::order`. To write parsed
+//! components back without any changes to the lines which were not modified (like their folding or the
+//! spelling of names), parse them with `ParserOptions::lossless` and encode them with `EncoderOptions::lossless`.
+//!
 //!
 //!
 //!
@@ -80,22 +170,60 @@
 //! [Parameters]:type.Parameters.html
 //! [`new`]:struct.Parser.html#method.new
 //! [`from_unbuffered`]:struct.Parser.html#method.from_unbuffered
+//! [`EventReader`]:struct.EventReader.html
+//! [`Event`]:enum.Event.html
+//! [`Span`]:struct.Span.html
+//! [`Name`]:struct.Name.html
+//! [`EncoderOptions::preserve_case`]:struct.EncoderOptions.html#method.preserve_case
+//! [`EncoderOptions`]:struct.EncoderOptions.html
+//! [`AsyncParser`]:struct.AsyncParser.html
+//! [`AsyncEncoder`]:struct.AsyncEncoder.html
+//! [`ContentLineCodec`]:struct.ContentLineCodec.html
+//! [`BorrowedParser`]:struct.BorrowedParser.html
+//! [`BorrowedComponent`]:struct.BorrowedComponent.html
+//! [`ParserOptions::lenient`]:struct.ParserOptions.html#method.lenient
+//! [`Limits`]:struct.Limits.html
+//! [`ComponentPath`]:struct.ComponentPath.html
 
 
 
 
-use std::collections::HashMap;
 use std::error::Error as StdError;
 use core::fmt;
 
+pub use crate::borrowed::{BorrowedComponent, BorrowedProperty};
 pub use crate::encoder::ComponentEncode;
-pub use crate::encoder::Encoder;
-pub use crate::parser::{Parser, rfc6868};
-pub use crate::parser::Error;
+pub use crate::encoder::{Encoder, EncoderOptions, FoldChar, LineEnding};
+pub use crate::parser::{BorrowedEvent, BorrowedParser, Charset, Cst, CstLine, CstNode, Decoding, Dialect, Diagnostic, Event, EventReader, Lexer, Limit, Limits, Parser, ParserOptions, PushParser, rfc6868, Severity, Token, TokenKind, vcard21};
+pub use crate::parser::{ComponentPath, Error, ErrorKind, PathSegment};
+pub use crate::name::{BorrowedName, Name};
+pub use crate::parameters::{BorrowedParameters, ParameterList, Parameters};
+pub use crate::raw::{RawComponent, RawProperty};
+pub use crate::span::{ComponentSpan, ParameterSpan, PropertySpan, Span};
+#[cfg(feature = "async")]
+pub use crate::parser::AsyncParser;
+#[cfg(feature = "fancy-errors")]
+pub use crate::parser::Report;
+#[cfg(feature = "rayon")]
+pub use crate::parser::parse_parallel;
+#[cfg(feature = "async")]
+pub use crate::async_encoder::AsyncEncoder;
+#[cfg(feature = "async")]
+pub use crate::codec::ContentLineCodec;
 
 
 mod parser;
 mod encoder;
+mod borrowed;
+mod name;
+mod parameters;
+mod raw;
+mod span;
+pub mod text;
+#[cfg(feature = "async")]
+mod async_encoder;
+#[cfg(feature = "async")]
+mod codec;
 
 #[cfg(test)]
 mod encoder_tests;
@@ -103,56 +231,84 @@ mod encoder_tests;
 #[cfg(test)]
 mod api_tests;
 
+#[cfg(all(test, feature = "async"))]
+mod async_tests;
+
 #[cfg(test)]
 mod test_helper;
 
 
-pub type Parameters = HashMap<String, Vec<String>>;
-
 const ALLOWED_PARAMETER_NAME_CHARS: &str = "-abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
 const COMP_BEGIN_S: &str = "BEGIN";
 const COMP_END_S: &str = "END";
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Property {
-	pub name: String,
+	//the group of the property (as in "item1.EMAIL"), which is only used by vCard.
+	pub group: Option<Name>,
+	pub name: Name,
 	pub value: String,
 	pub parameters: Parameters,
 	pub old_line: Option<(String, u32)>,
+	//where the property was found in the parsed input, None if it was not parsed.
+	pub span: Option<PropertySpan>,
+	//the physical lines the property was parsed from, if the parser kept them (see `ParserOptions::lossless`).
+	pub raw: Option<RawProperty>,
 }
 
 impl Property {
 	pub fn new_empty(name: String, value: String) -> Result<Self, InvalidNameError> {
-		Self::new(name, value, HashMap::new())
+		Self::new(name, value, Parameters::new())
 	}
 
 	pub fn new(name: String, value: String, parameters: Parameters) -> Result<Self, InvalidNameError> {
-		let x = Self { name, value, parameters, old_line: None };
+		let x = Self { group: None, name: Name::new(name), value, parameters, old_line: None, span: None, raw: None };
 		x.check()?;
 		Ok(x)
 	}
 
 	pub fn check(&self) -> Result<(), InvalidNameError> {
+		if let Some(group) = &self.group {
+			if let Some(c) = is_valid_name(group) {
+				return Err(InvalidNameError {
+					typ: NameType::Group,
+					violation: c,
+					name: group.to_string(),
+				});
+			}
+		}
 		if let Some(c) = is_valid_name(&self.name) {
 			return Err(InvalidNameError {
 				typ: NameType::Property,
 				violation: c,
-				name: self.name.clone(),
+				name: self.name.to_string(),
 			});
 		}
 
-		for p_name in self.parameters.keys() {
+		for (p_name, _) in &self.parameters {
 			if let Some(c) = is_valid_name(p_name) {
 				return Err(InvalidNameError {
 					typ: NameType::Parameter,
 					violation: c,
-					name: p_name.clone(),
+					name: p_name.to_string(),
 				});
 			}
 		}
 		Ok(())
 	}
 
+	pub fn set_group(&mut self, group: String) -> Result<(), InvalidNameError> {
+		if let Some(c) = is_valid_name(group.as_str()) {
+			return Err(InvalidNameError {
+				typ: NameType::Group,
+				violation: c,
+				name: group,
+			});
+		}
+		self.group = Some(Name::new(group));
+		Ok(())
+	}
+
 	pub fn add_param(&mut self, name: String, value: String) -> Result<(), InvalidNameError> {
 		if let Some(c) = is_valid_name(name.as_str()) {
 			return Err(InvalidNameError {
@@ -161,21 +317,83 @@ impl Property {
 				name: name.clone(),
 			});
 		}
-		self.parameters.entry(name)
-				.or_default().push(value);
+		self.parameters.add(name, value);
 		Ok(())
 	}
 
+	//get_param_value returns the values of the first occurrence of the parameter.
 	pub fn get_param_value(&self, name: &str) -> Option<&Vec<String>> {
 		self.parameters.get(name)
 	}
+
+	//get_param_values returns the values of all occurrences of the parameter.
+	pub fn get_param_values(&self, name: &str) -> Vec<&String> {
+		self.parameters.get_all(name).collect()
+	}
+
+	//text_value returns the value with all TEXT escape sequences (like "\," or "\n") replaced, see `text::unescape_text`.
+	pub fn text_value(&self) -> String {
+		text::unescape_text(&self.value)
+	}
+
+	//set_text_value escapes the text and sets it as the value, see `text::escape_text`.
+	pub fn set_text_value(&mut self, text: &str) {
+		self.value = text::escape_text(text);
+	}
+
+	//structured_value splits the value of a structured property (like N, ADR or ORG) into its components and
+	// these into their values, or the value of a list property (like CATEGORIES) into its values (which are
+	// the single component), see `text::split_structured`.
+	pub fn structured_value(&self) -> Vec<Vec<String>> {
+		text::split_structured(&self.value)
+	}
+
+	//set_structured_value escapes the values and sets them as the value, see `text::join_structured`.
+	pub fn set_structured_value<S: AsRef<str>>(&mut self, components: &[Vec<S>]) {
+		self.value = text::join_structured(components);
+	}
 }
 
 #[derive(Debug)]
 pub struct Component {
-	pub name: String,
+	pub name: Name,
 	pub properties: Vec<Property>,
 	pub sub_components: Vec<Component>,
+	//where the BEGIN and END lines were found in the parsed input, None if it was not parsed.
+	pub span: Option<ComponentSpan>,
+	//the order of the properties and sub-components, as they were parsed or added. The encoder writes them
+	// in this order, followed by those which are missing (properties first), so it has to be updated (or
+	// cleared) when children are removed or moved.
+	pub order: Vec<Child>,
+	//the BEGIN and END lines of the component, if the parser kept them (see `ParserOptions::lossless`).
+	pub raw: Option<RawComponent>,
+}
+
+//Child refers to a property or sub-component of a component by its index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Child {
+	Property(usize),
+	SubComponent(usize),
+}
+
+//ordered_children returns the valid entries of order (each child at most once), followed by the missing children.
+pub(crate) fn ordered_children(order: &[Child], properties: usize, sub_components: usize) -> Vec<Child> {
+	let mut seen_properties = vec![false; properties];
+	let mut seen_components = vec![false; sub_components];
+	let mut out = Vec::with_capacity(properties + sub_components);
+	for &child in order {
+		let seen = match child {
+			Child::Property(i) => seen_properties.get_mut(i),
+			Child::SubComponent(i) => seen_components.get_mut(i),
+		};
+		if let Some(seen @ false) = seen {
+			*seen = true;
+			out.push(child);
+		}
+	}
+	out.extend(seen_properties.iter().enumerate().filter(|(_, &seen)| !seen).map(|(i, _)| Child::Property(i)));
+	out.extend(seen_components.iter().enumerate().filter(|(_, &seen)| !seen).map(|(i, _)| Child::SubComponent(i)));
+	out
 }
 
 impl Component {
@@ -184,7 +402,8 @@ impl Component {
 	}
 
 	pub fn new(name: String, properties: Vec<Property>, sub_components: Vec<Component>) -> Result<Self, InvalidNameError> {
-		let c = Component { name, properties, sub_components };
+		let order = ordered_children(&[], properties.len(), sub_components.len());
+		let c = Component { name: Name::new(name), properties, sub_components, span: None, order, raw: None };
 		c.check()?;
 		Ok(c)
 	}
@@ -194,7 +413,7 @@ impl Component {
 			return Err(InvalidNameError {
 				typ: NameType::Component,
 				violation: c,
-				name: self.name.clone(),
+				name: self.name.to_string(),
 			});
 		}
 
@@ -219,34 +438,61 @@ impl Component {
 		out
 	}
 
+	//groups returns all groups used by the properties of this component, in the order of their first use.
+	pub fn groups(&self) -> Vec<&Name> {
+		let mut out: Vec<&Name> = Vec::new();
+		for group in self.properties.iter().filter_map(|p| p.group.as_ref()) {
+			if !out.contains(&group) {
+				out.push(group);
+			}
+		}
+		out
+	}
+
+	//find_group returns all properties of the given group.
+	pub fn find_group(&self, group: &str) -> Vec<&Property> {
+		self.properties.iter().filter(|p| p.group.as_ref().is_some_and(|g| g == group)).collect()
+	}
+
+	pub fn find_property_in_group(&self, group: &str, name: &str) -> Vec<&Property> {
+		self.find_group(group).into_iter().filter(|p| p.name == name).collect()
+	}
+
 	pub fn add_property(&mut self, p: Property) {
+		self.order.push(Child::Property(self.properties.len()));
 		self.properties.push(p)
 	}
 
 	pub fn add_sub_component(&mut self, c: Component) {
+		self.order.push(Child::SubComponent(self.sub_components.len()));
 		self.sub_components.push(c)
 	}
 
+	//children returns the indexes of all properties and sub-components in the order they are written in.
+	pub fn children(&self) -> Vec<Child> {
+		ordered_children(&self.order, self.properties.len(), self.sub_components.len())
+	}
+
 	pub fn encode_to_string(&self) -> String {
+		self.encode_to_string_with_options(&EncoderOptions::default())
+	}
+
+	pub fn encode_to_string_with_options(&self, options: &EncoderOptions) -> String {
 		let mut buf = vec![];
 
 		//there really should not be any io errors, as the target is only memory.
-		buf.encode_component(self).unwrap();
+		buf.encode_component_with_options(self, options).unwrap();
 
 		//there should also be no utf8 encoding errors, as the input is a structure of UTF8-Strings
-		// and we take care not to produce invalid characters.
-		String::from_utf8(buf).unwrap()
+		// and we take care not to produce invalid characters. Only raw lines written by a lossless encoder
+		// may be in another character set.
+		String::from_utf8(buf).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
 	}
 }
 
 
 pub fn is_valid_name(name: &str) -> Option<char> {
-	for (_, c) in name.char_indices() {
-		if !ALLOWED_PARAMETER_NAME_CHARS.contains(c) {
-			return Some(c);
-		}
-	}
-	None
+	name.chars().find(|&c| !ALLOWED_PARAMETER_NAME_CHARS.contains(c))
 }
 
 #[derive(Debug)]
@@ -267,6 +513,7 @@ impl fmt::Display for InvalidNameError {
 #[derive(Debug)]
 enum NameType {
 	Component,
+	Group,
 	Property,
 	Parameter,
 }
@@ -276,6 +523,7 @@ impl fmt::Display for NameType {
 		use NameType::*;
 		match self {
 			Component => write!(f, "component"),
+			Group => write!(f, "group"),
 			Property => write!(f, "property"),
 			Parameter => write!(f, "parameter"),
 		}
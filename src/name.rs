@@ -0,0 +1,147 @@
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::ops::Deref;
+use std::borrow::Cow;
+
+//Name is the name of a component, property or parameter. It keeps the spelling it was created with,
+// but compares (and hashes) case-insensitively, as names in content lines are not case-sensitive.
+#[derive(Clone, Default)]
+pub struct Name<S = String>(S);
+
+pub type BorrowedName<'a> = Name<Cow<'a, str>>;
+
+impl<S> Name<S> where S: AsRef<str> {
+	pub fn new(name: S) -> Self {
+		Name(name)
+	}
+
+	//as_str returns the name in its original spelling.
+	pub fn as_str(&self) -> &str {
+		self.0.as_ref()
+	}
+
+	pub fn get_ref(&self) -> &S {
+		&self.0
+	}
+
+	pub fn into_inner(self) -> S {
+		self.0
+	}
+}
+
+impl Name<Cow<'_, str>> {
+	pub fn into_owned(self) -> Name {
+		Name(self.0.into_owned())
+	}
+}
+
+impl<S> Deref for Name<S> where S: AsRef<str> {
+	type Target = str;
+
+	fn deref(&self) -> &str {
+		self.as_str()
+	}
+}
+
+impl<S> AsRef<str> for Name<S> where S: AsRef<str> {
+	fn as_ref(&self) -> &str {
+		self.as_str()
+	}
+}
+
+impl From<String> for Name {
+	fn from(name: String) -> Self {
+		Name(name)
+	}
+}
+
+impl From<&str> for Name {
+	fn from(name: &str) -> Self {
+		Name(name.to_string())
+	}
+}
+
+impl<'a> From<Cow<'a, str>> for BorrowedName<'a> {
+	fn from(name: Cow<'a, str>) -> Self {
+		Name(name)
+	}
+}
+
+impl From<Name> for String {
+	fn from(name: Name) -> Self {
+		name.0
+	}
+}
+
+impl<S, T> PartialEq<Name<T>> for Name<S> where S: AsRef<str>, T: AsRef<str> {
+	fn eq(&self, other: &Name<T>) -> bool {
+		self.as_str().eq_ignore_ascii_case(other.as_str())
+	}
+}
+
+impl<S> Eq for Name<S> where S: AsRef<str> {}
+
+impl<S> PartialEq<str> for Name<S> where S: AsRef<str> {
+	fn eq(&self, other: &str) -> bool {
+		self.as_str().eq_ignore_ascii_case(other)
+	}
+}
+
+impl<S> PartialEq<&str> for Name<S> where S: AsRef<str> {
+	fn eq(&self, other: &&str) -> bool {
+		self.as_str().eq_ignore_ascii_case(other)
+	}
+}
+
+impl<S> PartialEq<String> for Name<S> where S: AsRef<str> {
+	fn eq(&self, other: &String) -> bool {
+		self.as_str().eq_ignore_ascii_case(other)
+	}
+}
+
+impl<S> Hash for Name<S> where S: AsRef<str> {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		for b in self.as_str().bytes() {
+			state.write_u8(b.to_ascii_uppercase());
+		}
+		state.write_u8(0xff);
+	}
+}
+
+impl<S> fmt::Display for Name<S> where S: AsRef<str> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+		f.write_str(self.as_str())
+	}
+}
+
+impl<S> fmt::Debug for Name<S> where S: AsRef<str> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+		fmt::Debug::fmt(self.as_str(), f)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashSet;
+	use std::borrow::Cow;
+
+	use crate::Name;
+
+	#[test]
+	fn case_insensitive() {
+		let name = Name::from("X-MyApp-Flag");
+		assert_eq!(name, "x-myapp-FLAG");
+		assert_eq!(name, Name::new(Cow::Borrowed("X-MYAPP-FLAG")));
+		assert_ne!(name, "X-MyApp");
+		assert_eq!(name.as_str(), "X-MyApp-Flag");
+		assert_eq!(name.to_string(), "X-MyApp-Flag");
+	}
+
+	#[test]
+	fn hash() {
+		let mut set = HashSet::new();
+		set.insert(Name::from("Summary"));
+		assert!(set.contains(&Name::from("SUMMARY")));
+		assert!(!set.insert(Name::from("summary")));
+	}
+}
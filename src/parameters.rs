@@ -0,0 +1,214 @@
+use core::fmt;
+use std::borrow::Cow;
+use std::iter::FromIterator;
+use std::slice;
+use std::vec;
+
+use crate::Name;
+
+//ParameterList holds the parameters of a property in the order in which they appear in the content line.
+// Every entry is a single occurrence of a parameter with all of its values, so "TYPE=a,b" is one entry
+// with two values while "TYPE=a;TYPE=b" are two entries with one value each. Parameter names are
+// looked up case-insensitively.
+#[derive(Clone)]
+pub struct ParameterList<S> {
+	entries: Vec<(Name<S>, Vec<S>)>,
+}
+
+pub type Parameters = ParameterList<String>;
+pub type BorrowedParameters<'a> = ParameterList<Cow<'a, str>>;
+
+impl<S> ParameterList<S> where S: AsRef<str> {
+	pub fn new() -> Self {
+		ParameterList { entries: Vec::new() }
+	}
+
+	pub fn with_capacity(capacity: usize) -> Self {
+		ParameterList { entries: Vec::with_capacity(capacity) }
+	}
+
+	//len returns the number of entries, a parameter which occurs multiple times is counted every time.
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	pub fn contains_key(&self, name: &str) -> bool {
+		self.position(name).is_some()
+	}
+
+	//get returns the values of the first occurrence of the parameter.
+	pub fn get(&self, name: &str) -> Option<&Vec<S>> {
+		self.position(name).map(|i| &self.entries[i].1)
+	}
+
+	pub fn get_mut(&mut self, name: &str) -> Option<&mut Vec<S>> {
+		self.position(name).map(move |i| &mut self.entries[i].1)
+	}
+
+	//get_all returns the values of all occurrences of the parameter.
+	pub fn get_all<'s, 'n>(&'s self, name: &'n str) -> impl Iterator<Item=&'s S> + 'n where 's: 'n {
+		self.entries.iter()
+				.filter(move |(n, _)| n == name)
+				.flat_map(|(_, values)| values.iter())
+	}
+
+	//push appends a new occurrence of the parameter, even if it is already present.
+	pub fn push<N: Into<Name<S>>>(&mut self, name: N, values: Vec<S>) {
+		self.entries.push((name.into(), values));
+	}
+
+	//add appends the value to the first occurrence of the parameter, or appends a new occurrence if the
+	// parameter is not present yet.
+	pub fn add<N: Into<Name<S>>>(&mut self, name: N, value: S) {
+		let name = name.into();
+		match self.position(&name) {
+			Some(i) => self.entries[i].1.push(value),
+			None => self.entries.push((name, vec![value])),
+		}
+	}
+
+	//insert replaces the values of the first occurrence of the parameter and removes all other occurrences.
+	// The old values of the first occurrence are returned. If the parameter is not present yet, it is appended.
+	pub fn insert<N: Into<Name<S>>>(&mut self, name: N, values: Vec<S>) -> Option<Vec<S>> {
+		let name = name.into();
+		match self.position(&name) {
+			Some(i) => {
+				let old = std::mem::replace(&mut self.entries[i].1, values);
+				let mut j = 0;
+				self.entries.retain(|(n, _)| {
+					j += 1;
+					j - 1 <= i || *n != name
+				});
+				Some(old)
+			}
+			None => {
+				self.entries.push((name, values));
+				None
+			}
+		}
+	}
+
+	//remove removes all occurrences of the parameter and returns their values.
+	pub fn remove(&mut self, name: &str) -> Option<Vec<S>> {
+		self.position(name)?;
+		let mut out = Vec::new();
+		self.entries.retain_mut(|(n, values)| {
+			if n == name {
+				out.append(values);
+				false
+			} else {
+				true
+			}
+		});
+		Some(out)
+	}
+
+	//iter returns all entries in their original order.
+	pub fn iter(&self) -> slice::Iter<'_, (Name<S>, Vec<S>)> {
+		self.entries.iter()
+	}
+
+	fn position(&self, name: &str) -> Option<usize> {
+		self.entries.iter().position(|(n, _)| n == name)
+	}
+}
+
+impl<S> fmt::Debug for ParameterList<S> where S: AsRef<str> + fmt::Debug {
+	fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+		f.debug_map().entries(self.entries.iter().map(|(name, values)| (name, values))).finish()
+	}
+}
+
+impl<S> PartialEq for ParameterList<S> where S: AsRef<str> {
+	fn eq(&self, other: &Self) -> bool {
+		self.entries.len() == other.entries.len() && self.entries.iter().zip(&other.entries)
+				.all(|((a, a_values), (b, b_values))| a == b && a_values.iter().map(S::as_ref).eq(b_values.iter().map(S::as_ref)))
+	}
+}
+
+impl<S> Eq for ParameterList<S> where S: AsRef<str> {}
+
+impl<S> Default for ParameterList<S> {
+	fn default() -> Self {
+		ParameterList { entries: Vec::new() }
+	}
+}
+
+impl<S, N> FromIterator<(N, Vec<S>)> for ParameterList<S> where N: Into<Name<S>> {
+	fn from_iter<I: IntoIterator<Item=(N, Vec<S>)>>(iter: I) -> Self {
+		ParameterList { entries: iter.into_iter().map(|(name, values)| (name.into(), values)).collect() }
+	}
+}
+
+impl<S> IntoIterator for ParameterList<S> {
+	type Item = (Name<S>, Vec<S>);
+	type IntoIter = vec::IntoIter<(Name<S>, Vec<S>)>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.entries.into_iter()
+	}
+}
+
+impl<'s, S> IntoIterator for &'s ParameterList<S> {
+	type Item = &'s (Name<S>, Vec<S>);
+	type IntoIter = slice::Iter<'s, (Name<S>, Vec<S>)>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.entries.iter()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::Parameters;
+
+	fn params() -> Parameters {
+		vec![
+			("TYPE".to_string(), vec!["a".to_string(), "b".to_string()]),
+			("PREF".to_string(), vec!["1".to_string()]),
+			("TYPE".to_string(), vec!["c".to_string()]),
+		].into_iter().collect()
+	}
+
+	#[test]
+	fn get() {
+		let p = params();
+		assert_eq!(p.len(), 3);
+		assert_eq!(p.get("TYPE").unwrap(), &vec!["a", "b"]);
+		assert_eq!(p.get_all("TYPE").collect::<Vec<_>>(), vec!["a", "b", "c"]);
+		assert_eq!(p.get("type").unwrap(), &vec!["a", "b"]);
+		assert!(p.get("LANG").is_none());
+		assert!(!p.contains_key("LANG"));
+	}
+
+	#[test]
+	fn add() {
+		let mut p = params();
+		p.add("TYPE".to_string(), "d".to_string());
+		p.add("LANG".to_string(), "en".to_string());
+		let names: Vec<_> = p.iter().map(|(n, v)| format!("{}={}", n, v.join(","))).collect();
+		assert_eq!(names, vec!["TYPE=a,b,d", "PREF=1", "TYPE=c", "LANG=en"]);
+	}
+
+	#[test]
+	fn insert() {
+		let mut p = params();
+		assert_eq!(p.insert("TYPE".to_string(), vec!["x".to_string()]), Some(vec!["a".to_string(), "b".to_string()]));
+		let names: Vec<_> = p.iter().map(|(n, v)| format!("{}={}", n, v.join(","))).collect();
+		assert_eq!(names, vec!["TYPE=x", "PREF=1"]);
+		assert_eq!(p.insert("LANG".to_string(), vec![]), None);
+		assert_eq!(p.len(), 3);
+	}
+
+	#[test]
+	fn remove() {
+		let mut p = params();
+		assert_eq!(p.remove("TYPE"), Some(vec!["a".to_string(), "b".to_string(), "c".to_string()]));
+		assert_eq!(p.len(), 1);
+		assert_eq!(p.remove("TYPE"), None);
+	}
+}
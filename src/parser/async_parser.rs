@@ -0,0 +1,113 @@
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::future::poll_fn;
+use std::io;
+use std::mem;
+
+use futures_core::Stream;
+use tokio::io::AsyncBufRead;
+
+use crate::Component;
+use crate::parser::{Diagnostic, Error, ParserOptions, QueueParser};
+
+//AsyncParser parses components from an asynchronous reader. It is also a Stream of components.
+pub struct AsyncParser<R> {
+	input: R,
+	parser: QueueParser,
+	//the part of the current physical line which was read already
+	line: Vec<u8>,
+}
+
+impl<R> AsyncParser<R> where R: AsyncBufRead + Unpin {
+	pub fn new(input: R) -> Self {
+		Self::new_with_options(input, ParserOptions::default())
+	}
+
+	pub fn new_with_options(input: R, options: ParserOptions) -> Self {
+		AsyncParser {
+			input,
+			parser: QueueParser::new(options),
+			line: Vec::new(),
+		}
+	}
+
+	pub fn options(&self) -> &ParserOptions {
+		self.parser.options()
+	}
+
+	//take_diagnostics returns the diagnostics which were collected so far, see `ParserOptions::collect_diagnostics`.
+	pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+		self.parser.take_diagnostics()
+	}
+
+	pub async fn next_component(&mut self) -> Result<Option<Component>, Error> {
+		poll_fn(|cx| self.poll_next_component(cx)).await
+	}
+
+	fn poll_next_component(&mut self, cx: &mut Context) -> Poll<Result<Option<Component>, Error>> {
+		loop {
+			if let Some(c) = self.parser.next_component()? {
+				return Poll::Ready(Ok(Some(c)));
+			}
+			if self.parser.is_finished() {
+				return Poll::Ready(Ok(None));
+			}
+			match self.poll_read_line(cx) {
+				Poll::Ready(res) => res?,
+				Poll::Pending => return Poll::Pending,
+			}
+		}
+	}
+
+	//poll_read_line reads the next physical line and pushes it into the parser.
+	fn poll_read_line(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+		loop {
+			let buf = match Pin::new(&mut self.input).poll_fill_buf(cx) {
+				Poll::Ready(res) => res?,
+				Poll::Pending => return Poll::Pending,
+			};
+			if buf.is_empty() {
+				//Reached EOF, the last line doesn't need to end with '\n'
+				if !self.line.is_empty() {
+					self.parser.push_line(mem::take(&mut self.line));
+				}
+				self.parser.set_partial(None);
+				self.parser.finish();
+				return Poll::Ready(Ok(()));
+			}
+			match buf.iter().position(|&b| b == b'\n') {
+				Some(i) => {
+					self.line.extend_from_slice(&buf[..i]);
+					Pin::new(&mut self.input).consume(i + 1);
+					self.parser.push_line(mem::take(&mut self.line));
+					self.parser.set_partial(None);
+					return Poll::Ready(Ok(()));
+				}
+				None => {
+					let n = buf.len();
+					self.line.extend_from_slice(buf);
+					Pin::new(&mut self.input).consume(n);
+					//don't wait for the end of a line which is too long anyway, the parser rejects it.
+					if self.parser.options().limits.max_line_length.is_some_and(|max| self.line.len() > max + 2) {
+						self.parser.push_line(mem::take(&mut self.line));
+						self.parser.set_partial(None);
+						return Poll::Ready(Ok(()));
+					}
+					//the first byte of the next line may show that the previous line is complete, like in PushParser
+					if self.line.len() == n {
+						self.parser.set_partial(self.line.first().copied());
+						return Poll::Ready(Ok(()));
+					}
+				}
+			}
+		}
+	}
+}
+
+impl<R> Stream for AsyncParser<R> where R: AsyncBufRead + Unpin {
+	type Item = Result<Component, Error>;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		self.get_mut().poll_next_component(cx).map(Result::transpose)
+	}
+}
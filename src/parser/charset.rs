@@ -0,0 +1,99 @@
+//Character sets which lines and values can be decoded from, besides UTF-8.
+
+use std::string::FromUtf8Error;
+
+use crate::parser::vcard21::{parameter, parameters};
+
+//Charset is a character set which values can be decoded from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Charset {
+	//UTF-8 and its subset US-ASCII
+	Utf8,
+	//ISO-8859-1
+	Latin1,
+	//ISO-8859-15
+	Latin9,
+	Windows1252,
+}
+
+impl Charset {
+	//from_label returns the character set with the given name (case-insensitive), if it is supported.
+	pub fn from_label(label: &str) -> Option<Self> {
+		Some(match label.to_uppercase().as_str() {
+			"UTF-8" | "UTF8" | "US-ASCII" | "ASCII" => Charset::Utf8,
+			"ISO-8859-1" | "ISO8859-1" | "LATIN1" => Charset::Latin1,
+			"ISO-8859-15" | "ISO8859-15" | "LATIN9" => Charset::Latin9,
+			"WINDOWS-1252" | "CP1252" => Charset::Windows1252,
+			_ => return None,
+		})
+	}
+
+	//decode converts the bytes into a string. Only UTF-8 can fail, every byte is a character in the others.
+	pub fn decode(self, bytes: Vec<u8>) -> Result<String, FromUtf8Error> {
+		match self {
+			Charset::Utf8 => String::from_utf8(bytes),
+			Charset::Latin1 => Ok(bytes.iter().map(|&b| b as char).collect()),
+			Charset::Latin9 => Ok(bytes.iter().map(|&b| latin9(b)).collect()),
+			Charset::Windows1252 => Ok(bytes.iter().map(|&b| windows1252(b)).collect()),
+		}
+	}
+}
+
+fn latin9(b: u8) -> char {
+	match b {
+		0xA4 => '€',
+		0xA6 => 'Š',
+		0xA8 => 'š',
+		0xB4 => 'Ž',
+		0xB8 => 'ž',
+		0xBC => 'Œ',
+		0xBD => 'œ',
+		0xBE => 'Ÿ',
+		b => b as char,
+	}
+}
+
+fn windows1252(b: u8) -> char {
+	//the characters of 0x80..=0x9F, the bytes which are not assigned are mapped like in ISO-8859-1.
+	const HIGH: [char; 32] = [
+		'€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+		'\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+	];
+	match b {
+		0x80..=0x9F => HIGH[b as usize - 0x80],
+		b => b as char,
+	}
+}
+
+//declared_charset returns the character set declared with the CHARSET parameter of the line, if it is supported.
+pub(crate) fn declared_charset(line: &[u8]) -> Option<Charset> {
+	let params = parameters(line)?;
+	Charset::from_label(parameter(&params, "CHARSET")?)
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::parser::charset::*;
+
+	#[test]
+	fn charsets() {
+		assert_eq!(Charset::from_label("iso-8859-1"), Some(Charset::Latin1));
+		assert_eq!(Charset::from_label("US-ASCII"), Some(Charset::Utf8));
+		assert_eq!(Charset::from_label("SHIFT_JIS"), None);
+		assert_eq!(Charset::Latin1.decode(b"M\xfcller \xa4".to_vec()).unwrap(), "Müller ¤");
+		assert_eq!(Charset::Latin9.decode(b"M\xfcller \xa4".to_vec()).unwrap(), "Müller €");
+		assert_eq!(Charset::Windows1252.decode(b"\x80\x93a\x94\x81".to_vec()).unwrap(), "€“a”\u{81}");
+		assert!(Charset::Utf8.decode(b"\xfc".to_vec()).is_err());
+	}
+
+	#[test]
+	fn declared() {
+		assert_eq!(declared_charset(b"N;CHARSET=ISO-8859-1:a"), Some(Charset::Latin1));
+		assert_eq!(declared_charset(b"N;charset=\"windows-1252\";X=y:a"), Some(Charset::Windows1252));
+		assert_eq!(declared_charset(b"N;CHARSET=SHIFT_JIS:a"), None);
+		assert_eq!(declared_charset(b"N:CHARSET=ISO-8859-1"), None);
+		assert_eq!(declared_charset(b"N;X-CHARSET=ISO-8859-1:a"), None);
+		assert_eq!(declared_charset(b"N;X=\"a:b\";CHARSET=ISO-8859-1:M\xfcller"), Some(Charset::Latin1));
+		assert_eq!(declared_charset(b"N;X=\"CHARSET=ISO-8859-1\":a"), None);
+	}
+}
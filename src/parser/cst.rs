@@ -0,0 +1,383 @@
+use crate::{Component, ComponentSpan, Name, Parameters, Property, PropertySpan, ParameterSpan, Span};
+use crate::parser::{ErrorKind, Lexer, ParserOptions, rfc6868, Token, TokenKind, vcard21};
+
+//CstLine is an unfolded line together with all of its tokens, which cover all bytes of the line. Empty lines
+// only consist of a LineEnding token. The lines of an input are returned by `Lexer::next_line`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CstLine<'a> {
+	pub tokens: Vec<Token<'a>>,
+	//the span of all tokens, including the line ending
+	pub span: Span,
+}
+
+//CstNode is a node of the concrete syntax tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CstNode<'a> {
+	//a component with its BEGIN line, its properties and sub-components (in the order of the input) and its
+	// END line, which is None if it is missing.
+	Component { begin: CstLine<'a>, children: Vec<CstNode<'a>>, end: Option<CstLine<'a>> },
+	Property(CstLine<'a>),
+	//a line which neither begins a component nor is a property, like empty lines, malformed lines and END
+	// lines which don't end an open component.
+	Other(CstLine<'a>),
+}
+
+//Cst is the concrete syntax tree of an input: every byte of the input is part of exactly one token, so the
+// input can be reproduced from the tree. It is meant for tools like formatters and syntax highlighters, which
+// have to know about folds, quotes and the spelling of names. Unlike the parser, it never fails: lines which
+// can not be lexed end with an Error token and components without an END line are closed at the end of
+// their parent.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cst<'a> {
+	pub nodes: Vec<CstNode<'a>>,
+}
+
+impl<'a> Cst<'a> {
+	//parse builds the tree of the input. Only the dialect is taken from the options, lines may always end
+	// with LF and no limits apply.
+	pub fn parse(input: &'a str, options: &ParserOptions) -> Self {
+		let mut lexer = Lexer::new(input, options);
+		Cst { nodes: build(std::iter::from_fn(|| lexer.next_line()).collect()) }
+	}
+
+	//tokens returns all tokens in the order of the input.
+	pub fn tokens(&self) -> Vec<&Token<'a>> {
+		let mut out = Vec::new();
+		for node in &self.nodes {
+			node.collect_tokens(&mut out);
+		}
+		out
+	}
+
+	//components builds the top-level components, see `CstNode::to_component`.
+	pub fn components(&self) -> Vec<Component> {
+		self.nodes.iter().filter_map(CstNode::to_component).collect()
+	}
+}
+
+impl<'a> CstNode<'a> {
+	fn collect_tokens<'s>(&'s self, out: &mut Vec<&'s Token<'a>>) {
+		match self {
+			CstNode::Component { begin, children, end } => {
+				out.extend(&begin.tokens);
+				for child in children {
+					child.collect_tokens(out);
+				}
+				out.extend(end.iter().flat_map(|end| &end.tokens));
+			}
+			CstNode::Property(line) | CstNode::Other(line) => out.extend(&line.tokens),
+		}
+	}
+
+	//to_component builds the Component of a component node and None for all other nodes. Properties with
+	// errors are left out, values and parameters are not decoded in vCard 2.1. The spans of the component and
+	// its properties are the same as if it was parsed.
+	pub fn to_component(&self) -> Option<Component> {
+		let (begin, children, end) = match self {
+			CstNode::Component { begin, children, end } => (begin, children, end),
+			_ => return None,
+		};
+		let begin_span = begin.content_span();
+		let mut c = Component {
+			name: Name::new(begin.component_name()?.to_string()),
+			properties: vec![],
+			sub_components: vec![],
+			span: Some(ComponentSpan { begin: begin_span, end: end.as_ref().map_or(begin_span, CstLine::content_span) }),
+			order: vec![],
+			raw: None,
+		};
+		for child in children {
+			match child {
+				CstNode::Property(line) => if let Some(p) = line.to_property() {
+					c.add_property(p);
+				},
+				CstNode::Component { .. } => c.add_sub_component(child.to_component()?),
+				CstNode::Other(_) => {}
+			}
+		}
+		Some(c)
+	}
+}
+
+impl<'a> CstLine<'a> {
+	//significant returns the tokens without the byte order mark, folds and the line ending.
+	fn significant(&self) -> impl Iterator<Item=&Token<'a>> {
+		self.tokens.iter().filter(|t| !matches!(t.kind, TokenKind::Bom | TokenKind::Fold | TokenKind::LineEnding))
+	}
+
+	//error returns the kind of the error if the line could not be lexed.
+	pub fn error(&self) -> Option<ErrorKind> {
+		self.tokens.iter().find_map(|t| match t.kind {
+			TokenKind::Error(kind) => Some(kind),
+			_ => None,
+		})
+	}
+
+	//content_span returns the span of the line without the byte order mark and the line ending.
+	pub fn content_span(&self) -> Span {
+		let mut tokens = self.significant();
+		match (tokens.next(), tokens.last()) {
+			(Some(first), last) => {
+				let last = last.unwrap_or(first);
+				Span { end: last.span.end, end_line: last.span.end_line, ..first.span }
+			}
+			(None, _) => Span { end: self.span.start, end_line: self.span.start_line, ..self.span },
+		}
+	}
+
+	//component_name returns the name of the component the line begins or ends.
+	pub fn component_name(&self) -> Option<&str> {
+		self.significant().find(|t| t.kind == TokenKind::ComponentName).map(|t| t.text.as_ref())
+	}
+
+	//to_property builds the property of the line, None if the line is not a valid property.
+	pub fn to_property(&self) -> Option<Property> {
+		if self.error().is_some() {
+			return None;
+		}
+		let tokens: Vec<_> = self.significant().collect();
+		let mut p = Property {
+			group: None,
+			name: Name::default(),
+			value: String::new(),
+			parameters: Parameters::new(),
+			old_line: None,
+			span: None,
+			raw: None,
+		};
+		let mut span = PropertySpan { line: self.content_span(), ..PropertySpan::default() };
+		let mut param: Option<(String, Vec<String>)> = None;
+		//the name of the last parameter if it was a bare one (vCard 2.1), as in LineLexer
+		let mut bare = None;
+		let mut i = 0;
+		while i < tokens.len() {
+			let t = tokens[i];
+			match t.kind {
+				TokenKind::Group => {
+					p.group = Some(Name::new(t.text.to_string()));
+					span.group = Some(t.span);
+				}
+				TokenKind::Name => {
+					p.name = Name::new(t.text.to_string());
+					span.name = t.span;
+				}
+				TokenKind::ParamName => {
+					push_param(&mut p.parameters, param.take());
+					param = Some((t.text.to_string(), vec![]));
+					span.parameters.push(ParameterSpan { name: t.span, values: vec![] });
+					bare = None;
+				}
+				TokenKind::ParamValue | TokenKind::Quote => {
+					let is_bare = tokens[i - 1].kind == TokenKind::Semicolon;
+					//a quoted value consists of the opening quote, the value and the closing quote, its span includes the quotes.
+					let (value, value_span) = if t.kind == TokenKind::Quote {
+						let value = *tokens.get(i + 1).filter(|v| v.kind == TokenKind::ParamValue)?;
+						let close = tokens.get(i + 2).filter(|c| c.kind == TokenKind::Quote)?;
+						i += 2;
+						(value, Span { end: close.span.end, end_line: close.span.end_line, ..t.span })
+					} else {
+						(t, t.span)
+					};
+					if is_bare {
+						let name = if vcard21::ENCODINGS.iter().any(|e| e.eq_ignore_ascii_case(&value.text)) { "ENCODING" } else { "TYPE" };
+						if bare != Some(name) {
+							push_param(&mut p.parameters, param.take());
+							param = Some((name.to_string(), vec![]));
+							span.parameters.push(ParameterSpan { name: t.span, values: vec![] });
+						}
+						bare = Some(name);
+					}
+					let text = &value.text;
+					param.as_mut()?.1.push(if text.contains('^') { rfc6868::unescape_param_value(text) } else { text.to_string() });
+					span.parameters.last_mut()?.values.push(value_span);
+				}
+				TokenKind::Value => {
+					p.value = t.text.to_string();
+					span.value = t.span;
+				}
+				_ => {}
+			}
+			i += 1;
+		}
+		push_param(&mut p.parameters, param);
+		if p.name.as_str().is_empty() {
+			return None;
+		}
+		p.span = Some(span);
+		Some(p)
+	}
+}
+
+fn push_param(parameters: &mut Parameters, param: Option<(String, Vec<String>)>) {
+	if let Some((name, values)) = param {
+		parameters.push(name, values);
+	}
+}
+
+//build nests the lines into components. An END line ends the innermost open component with its name and
+// all components which were begun within it.
+fn build(lines: Vec<CstLine>) -> Vec<CstNode> {
+	struct Open<'a> {
+		begin: CstLine<'a>,
+		children: Vec<CstNode<'a>>,
+	}
+	fn close<'a>(stack: &mut Vec<Open<'a>>, top: &mut Vec<CstNode<'a>>, end: Option<CstLine<'a>>) {
+		let open = stack.pop().unwrap();
+		let node = CstNode::Component { begin: open.begin, children: open.children, end };
+		stack.last_mut().map_or(&mut *top, |parent| &mut parent.children).push(node);
+	}
+
+	let mut stack: Vec<Open> = Vec::new();
+	let mut top = Vec::new();
+	for line in lines {
+		let kind = if line.error().is_some() { None } else { line.significant().next().map(|t| t.kind) };
+		match kind {
+			Some(TokenKind::Begin) => stack.push(Open { begin: line, children: Vec::new() }),
+			Some(TokenKind::End) => {
+				let name = line.component_name().unwrap_or_default();
+				match stack.iter().rposition(|open| open.begin.component_name().is_some_and(|n| n.eq_ignore_ascii_case(name))) {
+					Some(i) => {
+						while stack.len() > i + 1 {
+							close(&mut stack, &mut top, None);
+						}
+						close(&mut stack, &mut top, Some(line));
+					}
+					None => stack.last_mut().map_or(&mut top, |parent| &mut parent.children).push(CstNode::Other(line)),
+				}
+			}
+			Some(TokenKind::Name | TokenKind::Group) => stack.last_mut().map_or(&mut top, |parent| &mut parent.children).push(CstNode::Property(line)),
+			_ => stack.last_mut().map_or(&mut top, |parent| &mut parent.children).push(CstNode::Other(line)),
+		}
+	}
+	while !stack.is_empty() {
+		close(&mut stack, &mut top, None);
+	}
+	top
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+
+	use crate::{ErrorKind, Parser, ParserOptions};
+	use crate::parser::cst::*;
+	use crate::test_helper::assert_comp_equal;
+
+	//assert_lossless checks that the tokens cover the whole input without gaps.
+	fn assert_lossless<'a>(input: &'a str, options: &ParserOptions) -> Cst<'a> {
+		let cst = Cst::parse(input, options);
+		let mut offset = 0;
+		for t in cst.tokens() {
+			assert_eq!(t.span.start, offset, "gap before {:?}", t);
+			offset = t.span.end;
+		}
+		assert_eq!(offset, input.len());
+		cst
+	}
+
+	fn kinds<'s>(line: &'s CstLine) -> Vec<(TokenKind, &'s str)> {
+		line.tokens.iter().map(|t| (t.kind, t.text.as_ref())).collect()
+	}
+
+	#[test]
+	fn tokens() {
+		let input = "BEGIN:VCARD\r\nitem1.TEL;TYPE=home,\"a:b\";X=:12\r\n 3\r\nEND:VCARD";
+		let cst = assert_lossless(input, &ParserOptions::default());
+		let (begin, children, end) = match &cst.nodes[..] {
+			[CstNode::Component { begin, children, end: Some(end) }] => (begin, children, end),
+			nodes => panic!("unexpected nodes {:?}", nodes),
+		};
+		use TokenKind::*;
+		assert_eq!(kinds(begin), vec![(Begin, "BEGIN"), (Colon, ":"), (ComponentName, "VCARD"), (LineEnding, "\r\n")]);
+		let property = match &children[..] {
+			[CstNode::Property(line)] => line,
+			children => panic!("unexpected children {:?}", children),
+		};
+		assert_eq!(kinds(property), vec![
+			(Group, "item1"), (Dot, "."), (Name, "TEL"), (Semicolon, ";"), (ParamName, "TYPE"), (Equals, "="),
+			(ParamValue, "home"), (Comma, ","), (Quote, "\""), (ParamValue, "a:b"), (Quote, "\""), (Semicolon, ";"),
+			(ParamName, "X"), (Equals, "="), (ParamValue, ""), (Colon, ":"), (Value, "123"), (LineEnding, "\r\n"),
+		]);
+		//the value contains the fold
+		let value = &property.tokens[16];
+		assert_eq!((value.span.start, value.span.end, value.span.start_line, value.span.end_line, value.span.column), (42, 48, 2, 3, 30));
+		assert_eq!(kinds(end), vec![(End, "END"), (Colon, ":"), (ComponentName, "VCARD")]);
+	}
+
+	#[test]
+	fn folds_and_empty_lines() {
+		let input = "\u{feff}\nBEGIN:A\n\nX;Y\n =1:\r\n\tv\nEND:A\n\n";
+		let cst = assert_lossless(input, &ParserOptions::default());
+		use TokenKind::*;
+		match &cst.nodes[..] {
+			[CstNode::Other(empty), CstNode::Component { children, .. }, CstNode::Other(last)] => {
+				assert_eq!(kinds(empty), vec![(Bom, "\u{feff}"), (LineEnding, "\n")]);
+				assert_eq!(kinds(last), vec![(LineEnding, "\n")]);
+				assert!(matches!(&children[0], CstNode::Other(line) if kinds(line) == vec![(LineEnding, "\n")]));
+				match &children[1] {
+					CstNode::Property(line) => assert_eq!(kinds(line), vec![
+						(Name, "X"), (Semicolon, ";"), (ParamName, "Y"), (Fold, "\n "), (Equals, "="), (ParamValue, "1"),
+						(Colon, ":"), (Fold, "\r\n\t"), (Value, "v"), (LineEnding, "\n"),
+					]),
+					node => panic!("unexpected node {:?}", node),
+				}
+			}
+			nodes => panic!("unexpected nodes {:?}", nodes),
+		}
+	}
+
+	#[test]
+	fn errors() {
+		let input = "END:A\r\nBEGIN:A\r\nBEGIN:B\r\nX;Y=\"a\r\nBEGIN:a b\r\nEND:A\r\n";
+		let cst = assert_lossless(input, &ParserOptions::default());
+		let (stray, children) = match &cst.nodes[..] {
+			[CstNode::Other(stray), CstNode::Component { children, end: Some(_), .. }] => (stray, children),
+			nodes => panic!("unexpected nodes {:?}", nodes),
+		};
+		assert_eq!(stray.component_name(), Some("A"));
+		//B is closed by the END line of A
+		let children = match &children[..] {
+			[CstNode::Component { children, end: None, .. }] => children,
+			children => panic!("unexpected children {:?}", children),
+		};
+		use TokenKind::*;
+		match &children[..] {
+			[CstNode::Other(quote), CstNode::Other(name)] => {
+				assert_eq!(kinds(quote)[4..], [(Error(ErrorKind::UnterminatedQuotedValue), "\"a"), (LineEnding, "\r\n")]);
+				assert_eq!(kinds(name)[2..], [(Error(ErrorKind::InvalidComponentName), "a b"), (LineEnding, "\r\n")]);
+			}
+			children => panic!("unexpected children {:?}", children),
+		}
+	}
+
+	#[test]
+	fn components() {
+		let input = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nSUMMARY;LANGUAGE=de;X=\"\",a^'b:Tr\r\n effen\r\nEND:VEVENT\r\nX-A;B=1:2\r\nEND:VCALENDAR\r\n";
+		let cst = assert_lossless(input, &ParserOptions::default());
+		let got = cst.components();
+		let expected = Parser::new(Cursor::new(input)).next_component().unwrap().unwrap();
+		assert_eq!(got.len(), 1);
+		assert_comp_equal(&got[0], &expected);
+		assert_eq!(got[0].span, expected.span);
+		assert_eq!(got[0].children(), expected.children());
+		assert_eq!(got[0].sub_components[0].properties[0].span, expected.sub_components[0].properties[0].span);
+		assert_eq!(got[0].properties[1].span, expected.properties[1].span);
+	}
+
+	#[test]
+	fn vcard21() {
+		let input = "BEGIN:VCARD\nTEL;HOME;VOICE;ENCODING=QUOTED-PRINTABLE:1=\n2\nEND:VCARD\n";
+		let options = ParserOptions::vcard21();
+		let cst = assert_lossless(input, &options);
+		let tel = &cst.components()[0].properties[0];
+		assert_eq!(tel.parameters.get_all("TYPE").collect::<Vec<_>>(), vec!["HOME", "VOICE"]);
+		assert_eq!(tel.value, "12");
+		match &cst.nodes[0] {
+			CstNode::Component { children, .. } => match &children[0] {
+				CstNode::Property(line) => assert_eq!(kinds(line)[10..], [(TokenKind::Value, "12"), (TokenKind::LineEnding, "\n")]),
+				node => panic!("unexpected node {:?}", node),
+			},
+			node => panic!("unexpected node {:?}", node),
+		}
+	}
+}
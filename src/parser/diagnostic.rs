@@ -0,0 +1,47 @@
+use core::fmt;
+
+use crate::parser::ErrorKind;
+use crate::Span;
+
+//Diagnostic describes a problem which was found while parsing with `ParserOptions::collect_diagnostics`.
+// Instead of failing, the parser skips or repairs the offending line and records a Diagnostic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+	pub severity: Severity,
+	//the part of the input the problem was found in
+	pub span: Span,
+	//the kind of problem, see `Error::kind`
+	pub kind: ErrorKind,
+	//the message of the error, including the context of the line
+	pub message: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+	//the input does not conform to the RFCs, but was understood anyway
+	Warning,
+	//the input could not be understood, a part of it was skipped or had to be guessed
+	Error,
+}
+
+impl fmt::Display for Severity {
+	fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+		match self {
+			Severity::Warning => write!(f, "warning"),
+			Severity::Error => write!(f, "error"),
+		}
+	}
+}
+
+impl Diagnostic {
+	//code returns the stable identifier of the kind of problem.
+	pub fn code(&self) -> &'static str {
+		self.kind.code()
+	}
+}
+
+impl fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+		write!(f, "{}[{}]: {}", self.severity, self.code(), self.message)
+	}
+}
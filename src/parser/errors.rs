@@ -5,27 +5,317 @@ use std::io;
 use std::string::FromUtf8Error;
 
 use crate::parser::errors::Error::*;
-use crate::parser::line_lexer::Item;
+use crate::parser::options::Limit;
+use crate::parser::path::ComponentPath;
 
+//Error is returned by the parsers. The variants describe where the error was found, the accessor methods
+// (kind, line, column, offending_text, expected, ...) give uniform access to this information.
+#[non_exhaustive]
 #[derive(Debug)]
 pub enum Error {
-	Malformed(String, String, usize, (String, u32)),
-	CRLF(Vec<u8>, u32, bool),
-	UnexpectedEOF(String, u32),
+	//the line does not conform to the content line syntax.
+	#[non_exhaustive]
+	Malformed {
+		kind: ErrorKind,
+		//what went wrong, without the context
+		message: String,
+		//the part of the line the error refers to, can be empty at the end of the line
+		text: String,
+		//the tokens which would have been accepted instead of text
+		expected: Vec<String>,
+		//the position of text in the unfolded line
+		pos: usize,
+		//the whole unfolded line and its number
+		line: String,
+		line_number: u32,
+		//the components which were open when the error occurred
+		path: ComponentPath,
+	},
+	//the line does not end with CRLF, line contains the line without its line ending.
+	#[non_exhaustive]
+	CRLF {
+		line: Vec<u8>,
+		line_number: u32,
+		has_next: bool,
+		path: ComponentPath,
+	},
+	//the input ended before the component with the name `expected` was ended.
+	#[non_exhaustive]
+	UnexpectedEOF {
+		expected: String,
+		line_number: u32,
+		path: ComponentPath,
+	},
 	Io(io::Error),
-	Utf8(FromUtf8Error),
+	//the line is not valid UTF-8.
+	#[non_exhaustive]
+	Utf8 {
+		error: FromUtf8Error,
+		line_number: u32,
+		//the position of the invalid sequence in the unfolded line
+		pos: usize,
+		//the byte offset of the invalid sequence in the input
+		offset: usize,
+		path: ComponentPath,
+	},
+	//the parser skipped the lines first_line..=last_line to recover from error.
+	#[non_exhaustive]
+	Recovered {
+		error: Box<Error>,
+		first_line: u32,
+		last_line: u32,
+	},
+	//the limit was exceeded in the given line, max is its configured maximum.
+	#[non_exhaustive]
+	LimitExceeded {
+		limit: Limit,
+		max: usize,
+		line_number: u32,
+		path: ComponentPath,
+	},
 }
 
-impl Error {
-	pub fn new(i: Item, msg: String, line: (String, u32)) -> Self {
-		Malformed(msg, i.val, i.pos, line)
+//ErrorKind identifies what went wrong, independently of where it happened. Every kind has a stable code
+// which does not change between versions and can be used to identify the error in other programs.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+	//the property name (or group) is empty or contains invalid characters
+	InvalidName,
+	//the property name following a group is empty or contains invalid characters
+	InvalidGroupedName,
+	//BEGIN or END is not followed by ':'
+	ExpectedColon,
+	//BEGIN: or END: is not followed by a component name
+	EmptyComponentName,
+	//the component name contains invalid characters
+	InvalidComponentName,
+	//the property name is not followed by ':' or ';'
+	ExpectedColonOrSemicolon,
+	//';' is not followed by a parameter name
+	EmptyParameterName,
+	//the parameter name is not followed by '='
+	ExpectedEquals,
+	//a quoted parameter value is not terminated by '"'
+	UnterminatedQuotedValue,
+	//the parameter value is not followed by ',', ':' or ';'
+	ExpectedParameterSeparator,
+	//the property value contains an invalid character
+	InvalidCharacter,
+	//a property or END line was found outside of a component
+	ExpectedBegin,
+	//the END line does not match the open component
+	MismatchedEnd,
+	//the line ends with LF instead of CRLF
+	MissingCarriageReturn,
+	//the input ended while a component was still open
+	UnexpectedEof,
+	Io,
+	InvalidUtf8,
+	LimitExceeded,
+	//the character set declared with CHARSET is not supported (vCard 2.1)
+	UnsupportedCharset,
+}
+
+impl ErrorKind {
+	//code returns a short identifier of the kind, which does not change between versions.
+	pub fn code(&self) -> &'static str {
+		match self {
+			ErrorKind::InvalidName => "invalid-name",
+			ErrorKind::InvalidGroupedName => "invalid-grouped-name",
+			ErrorKind::ExpectedColon => "expected-colon",
+			ErrorKind::EmptyComponentName => "empty-component-name",
+			ErrorKind::InvalidComponentName => "invalid-component-name",
+			ErrorKind::ExpectedColonOrSemicolon => "expected-colon-or-semicolon",
+			ErrorKind::EmptyParameterName => "empty-parameter-name",
+			ErrorKind::ExpectedEquals => "expected-equals",
+			ErrorKind::UnterminatedQuotedValue => "unterminated-quoted-value",
+			ErrorKind::ExpectedParameterSeparator => "expected-parameter-separator",
+			ErrorKind::InvalidCharacter => "invalid-character",
+			ErrorKind::ExpectedBegin => "expected-begin",
+			ErrorKind::MismatchedEnd => "mismatched-end",
+			ErrorKind::MissingCarriageReturn => "missing-carriage-return",
+			ErrorKind::UnexpectedEof => "unexpected-eof",
+			ErrorKind::Io => "io",
+			ErrorKind::InvalidUtf8 => "invalid-utf8",
+			ErrorKind::LimitExceeded => "limit-exceeded",
+			ErrorKind::UnsupportedCharset => "unsupported-charset",
+		}
 	}
+
+	//message returns a description of the kind, without any context.
+	pub fn message(&self) -> &'static str {
+		match self {
+			ErrorKind::InvalidName => "expected one or more alphanumerical characters or '-'",
+			ErrorKind::InvalidGroupedName => "expected one or more alphanumerical characters or '-' after the group",
+			ErrorKind::ExpectedColon => "expected ':'",
+			ErrorKind::EmptyComponentName => "component name mustn't have length 0",
+			ErrorKind::InvalidComponentName => "unexpected character, expected eol, alphanumeric or '-'",
+			ErrorKind::ExpectedColonOrSemicolon => "expected ':' or ';'",
+			ErrorKind::EmptyParameterName => "parameter name must not be empty",
+			ErrorKind::ExpectedEquals => "expected '='",
+			ErrorKind::UnterminatedQuotedValue => "expected '\"' or other non-control-characters",
+			ErrorKind::ExpectedParameterSeparator => "expected ',', ':' or ';'",
+			ErrorKind::InvalidCharacter => "unexpected character, expected eol",
+			ErrorKind::ExpectedBegin => "expected BEGIN",
+			ErrorKind::MismatchedEnd => "expected END of the open component",
+			ErrorKind::MissingCarriageReturn => "expected CR ('\\r') before LF",
+			ErrorKind::UnexpectedEof => "unexpected end of file or stream",
+			ErrorKind::Io => "IO error",
+			ErrorKind::InvalidUtf8 => "invalid UTF-8",
+			ErrorKind::LimitExceeded => "limit exceeded",
+			ErrorKind::UnsupportedCharset => "unsupported character set",
+		}
+	}
+
+	//expected returns the tokens which would have been accepted, "eol" stands for the end of the line.
+	pub fn expected(&self) -> &'static [&'static str] {
+		match self {
+			ErrorKind::InvalidName | ErrorKind::InvalidGroupedName | ErrorKind::EmptyComponentName
+			| ErrorKind::EmptyParameterName => &["alphanumeric", "-"],
+			ErrorKind::ExpectedColon => &[":"],
+			ErrorKind::InvalidComponentName => &["eol", "alphanumeric", "-"],
+			ErrorKind::ExpectedColonOrSemicolon => &[":", ";"],
+			ErrorKind::ExpectedEquals => &["="],
+			ErrorKind::UnterminatedQuotedValue => &["\""],
+			ErrorKind::ExpectedParameterSeparator => &[",", ":", ";"],
+			ErrorKind::InvalidCharacter => &["eol"],
+			ErrorKind::ExpectedBegin => &["BEGIN"],
+			ErrorKind::MissingCarriageReturn => &["\r"],
+			ErrorKind::MismatchedEnd | ErrorKind::UnexpectedEof | ErrorKind::Io | ErrorKind::InvalidUtf8
+			| ErrorKind::LimitExceeded | ErrorKind::UnsupportedCharset => &[],
+		}
+	}
+}
+
+impl fmt::Display for ErrorKind {
+	fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+		f.write_str(self.message())
+	}
+}
+
+impl Error {
 	pub fn crlf_error(bytes: Vec<u8>, linenum: u32, has_next: bool) -> Self {
-		CRLF(bytes, linenum, has_next)
+		CRLF { line: bytes, line_number: linenum, has_next, path: ComponentPath::default() }
 	}
 
 	pub fn eof_error(msg: String, l: u32) -> Self {
-		UnexpectedEOF(msg, l)
+		UnexpectedEOF { expected: msg, line_number: l, path: ComponentPath::default() }
+	}
+
+	pub fn limit_error(limit: Limit, max: usize, l: u32) -> Self {
+		LimitExceeded { limit, max, line_number: l, path: ComponentPath::default() }
+	}
+
+	pub fn utf8_error(error: FromUtf8Error, l: u32, pos: usize, offset: usize) -> Self {
+		Utf8 { error, line_number: l, pos, offset, path: ComponentPath::default() }
+	}
+
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			Malformed { kind, .. } => *kind,
+			CRLF { .. } => ErrorKind::MissingCarriageReturn,
+			UnexpectedEOF { .. } => ErrorKind::UnexpectedEof,
+			Io(_) => ErrorKind::Io,
+			Utf8 { .. } => ErrorKind::InvalidUtf8,
+			Recovered { error, .. } => error.kind(),
+			LimitExceeded { .. } => ErrorKind::LimitExceeded,
+		}
+	}
+
+	//code returns a short identifier of the kind of error, which does not change between versions.
+	pub fn code(&self) -> &'static str {
+		self.kind().code()
+	}
+
+	//line returns the number of the line the error was found in. For errors in folded lines, this is the
+	// line the unfolded line started in.
+	pub fn line(&self) -> Option<u32> {
+		match self {
+			Malformed { line_number, .. } | CRLF { line_number, .. } | UnexpectedEOF { line_number, .. }
+			| LimitExceeded { line_number, .. } | Utf8 { line_number, .. } => Some(*line_number),
+			Recovered { error, .. } => error.line(),
+			Io(_) => None,
+		}
+	}
+
+	//column returns the column in the unfolded line the error was found in, counted in bytes from 1.
+	pub fn column(&self) -> Option<usize> {
+		match self {
+			Malformed { pos, .. } | Utf8 { pos, .. } => Some(pos + 1),
+			CRLF { line, .. } => Some(line.len() + 1),
+			Recovered { error, .. } => error.column(),
+			_ => None,
+		}
+	}
+
+	//offset returns the byte offset of the error in the input, if it is known.
+	pub fn offset(&self) -> Option<usize> {
+		match self {
+			Utf8 { offset, .. } => Some(*offset),
+			Recovered { error, .. } => error.offset(),
+			_ => None,
+		}
+	}
+
+	//offending_text returns the part of the line the error refers to.
+	pub fn offending_text(&self) -> Option<&str> {
+		match self {
+			Malformed { text, .. } => Some(text),
+			Recovered { error, .. } => error.offending_text(),
+			_ => None,
+		}
+	}
+
+	//expected returns the tokens which would have been accepted instead of the offending text.
+	pub fn expected(&self) -> Vec<String> {
+		match self {
+			Malformed { expected, .. } => expected.clone(),
+			UnexpectedEOF { expected, .. } => vec![format!("END:{}", expected)],
+			Recovered { error, .. } => error.expected(),
+			_ => self.kind().expected().iter().map(|s| s.to_string()).collect(),
+		}
+	}
+
+	//source_line returns the (unfolded) line the error was found in, if it is known.
+	pub fn source_line(&self) -> Option<&str> {
+		match self {
+			Malformed { line, .. } => Some(line),
+			CRLF { line, .. } => std::str::from_utf8(line).ok(),
+			Recovered { error, .. } => error.source_line(),
+			_ => None,
+		}
+	}
+
+	//path returns the components which were open when the error occurred. It is empty if the error occurred
+	// outside of a component and None for errors which don't belong to a line.
+	pub fn path(&self) -> Option<&ComponentPath> {
+		match self {
+			Malformed { path, .. } | CRLF { path, .. } | UnexpectedEOF { path, .. } | LimitExceeded { path, .. }
+			| Utf8 { path, .. } => Some(path),
+			Recovered { error, .. } => error.path(),
+			Io(_) => None,
+		}
+	}
+
+	//with_path sets the path of the error.
+	pub(crate) fn with_path(mut self, p: ComponentPath) -> Self {
+		match &mut self {
+			Malformed { path, .. } | CRLF { path, .. } | UnexpectedEOF { path, .. } | LimitExceeded { path, .. }
+			| Utf8 { path, .. } => *path = p,
+			//the wrapped error already has its path
+			Recovered { .. } | Io(_) => {}
+		}
+		self
+	}
+
+	//skipped_lines returns the first and last line which were skipped to recover from this error,
+	// if the parser was configured to recover from errors.
+	pub fn skipped_lines(&self) -> Option<(u32, u32)> {
+		match self {
+			Recovered { first_line, last_line, .. } => Some((*first_line, *last_line)),
+			_ => None,
+		}
 	}
 }
 
@@ -35,18 +325,13 @@ impl From<io::Error> for Error {
 	}
 }
 
-impl From<FromUtf8Error> for Error {
-	fn from(e: FromUtf8Error) -> Self {
-		Utf8(e)
-	}
-}
-
 impl StdError for Error {
 	fn source(&self) -> Option<&(dyn StdError + 'static)> {
 		match self {
-			Malformed(..) | CRLF(..) | UnexpectedEOF(..) => None,
+			Malformed { .. } | CRLF { .. } | UnexpectedEOF { .. } | LimitExceeded { .. } => None,
 			Io(e) => Some(e),
-			Utf8(e) => Some(e),
+			Utf8 { error, .. } => Some(error),
+			Recovered { error, .. } => Some(error.as_ref()),
 		}
 	}
 }
@@ -54,30 +339,37 @@ impl StdError for Error {
 impl fmt::Display for Error {
 	fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
 		match self {
-			Io(e) => (e as &Display).fmt(f),
-			Utf8(e) => (e as &Display).fmt(f),
-			CRLF(buf, line, _) => {
+			Io(e) => (e as &dyn Display).fmt(f),
+			Utf8 { error, line_number, offset, .. } => {
+				writeln!(f, "line {}: \tinvalid UTF-8 at byte offset {}: {}", line_number, offset, error)
+			}
+			CRLF { line: buf, line_number: line, .. } => {
 				if buf.is_empty() {
 					writeln!(f, "line {}: expected CR ('\\r') before LF in empty line", line)
 				} else {
 					match String::from_utf8(buf.clone()) {
-						Err(e) => (&e as &Display).fmt(f),
-						Ok(s) => error_msg(f, (s, *line), "expected CR ('\\r') before LF", buf.len(), buf.len()),
+						Err(e) => (&e as &dyn Display).fmt(f),
+						Ok(s) => error_msg(f, (s, *line), ErrorKind::MissingCarriageReturn.message(), buf.len(), buf.len()),
 					}
 				}
 			}
-			Malformed(msg, val, pos, line) => {
-				if msg.is_empty() {
-					//take length of char at pos or 0
-					let x=line.0[*pos..].chars().next().map_or(0,|c|c.len_utf8());
-					error_msg(f, line.clone(), val.as_str(), *pos, pos + x)
-				} else {
-					error_msg(f, line.clone(), msg.as_str(), *pos, pos + val.len())
-				}
+			Malformed { message, text, pos, line, line_number, .. } => {
+				error_msg(f, (line.clone(), *line_number), message, *pos, pos + text.len())
+			}
+			UnexpectedEOF { expected, line_number, .. } => {
+				writeln!(f, "line {}: \tunexpected end of file or stream, expected END:{}", line_number, expected)
 			}
-			UnexpectedEOF(comp_name, line) => {
-				writeln!(f, "line {}: \tunexpected end of file or stream, expected END:{}", line, comp_name)
+			LimitExceeded { limit, max, line_number, .. } => {
+				writeln!(f, "line {}: \tlimit exceeded: the {} must not be larger than {}", line_number, limit, max)
 			}
+			Recovered { error, first_line, last_line } => {
+				(error.as_ref() as &dyn Display).fmt(f)?;
+				return writeln!(f, "lines {}-{}: \tskipped to recover from the error", first_line, last_line);
+			}
+		}?;
+		match self.path().and_then(|p| p.last().map(|last| (p, last))) {
+			Some((path, last)) => writeln!(f, "\tin {} (BEGIN in line {})", path, last.line),
+			None => Ok(()),
 		}
 	}
 }
@@ -86,14 +378,17 @@ const ERROR_CONTEXT_RADIUS: usize = 20;
 
 fn error_msg(f: &mut fmt::Formatter, line: (String, u32), msg: &str, pos1: usize, pos2: usize) -> Result<(), fmt::Error> {
 	let ctx = line.0.as_str();
+	//the positions and the context are moved to char boundaries, which is only necessary for non-ASCII lines.
+	let pos1 = floor_char_boundary(ctx, pos1);
+	let pos2 = floor_char_boundary(ctx, pos2).max(pos1);
 	let prefix = if pos1 > ERROR_CONTEXT_RADIUS {
-		"...".to_owned() + &ctx[pos1 - ERROR_CONTEXT_RADIUS..pos1]
+		"...".to_owned() + &ctx[floor_char_boundary(ctx, pos1 - ERROR_CONTEXT_RADIUS)..pos1]
 	} else {
 		ctx[..pos1].to_owned()
 	};
 
 	let suffix = if ctx.len() > ERROR_CONTEXT_RADIUS + pos2 {
-		ctx[pos1..pos2 + ERROR_CONTEXT_RADIUS].to_owned() + "..."
+		ctx[pos1..floor_char_boundary(ctx, pos2 + ERROR_CONTEXT_RADIUS)].to_owned() + "..."
 	} else if pos1 < ctx.len() {
 		ctx[pos1..].to_owned()
 	} else {
@@ -107,3 +402,12 @@ fn error_msg(f: &mut fmt::Formatter, line: (String, u32), msg: &str, pos1: usize
 		_ => writeln!(f, "line {}: \t{}: {} >{}< {}", line.1, msg, prefix, &suffix[..len], &suffix[len..]),
 	}
 }
+
+//floor_char_boundary returns the last char boundary at or before pos.
+fn floor_char_boundary(s: &str, pos: usize) -> usize {
+	let mut pos = pos.min(s.len());
+	while !s.is_char_boundary(pos) {
+		pos -= 1;
+	}
+	pos
+}
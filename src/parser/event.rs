@@ -0,0 +1,190 @@
+use std::io::{BufRead, BufReader, Read};
+
+use crate::{BorrowedComponent, BorrowedName, BorrowedProperty, Child, Component, ComponentSpan, Name, Property, RawComponent, Span};
+use crate::parser::{Diagnostic, Error, ParserCore, ParserOptions};
+use crate::parser::reader::ReadLines;
+
+//Event is the result of parsing a single (unfolded) line. Unless an error is returned, every BeginComponent
+// event is eventually followed by a matching EndComponent event, the events in between make up the content
+// of the component. When recovering from an error (see `ParserOptions::recover_from_errors`), the open
+// components are abandoned without EndComponent events and the events continue with the next top-level one.
+// The spans of BeginComponent and EndComponent point to the whole BEGIN and END lines.
+//Properties are not boxed, events are usually consumed right away and boxing would cost an allocation per line.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+pub enum Event {
+	BeginComponent(Name, Span),
+	Property(Property),
+	EndComponent(Name, Span),
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
+pub enum BorrowedEvent<'a> {
+	BeginComponent(BorrowedName<'a>, Span),
+	Property(BorrowedProperty<'a>),
+	EndComponent(BorrowedName<'a>, Span),
+}
+
+impl BorrowedEvent<'_> {
+	pub fn into_owned(self) -> Event {
+		match self {
+			BorrowedEvent::BeginComponent(name, span) => Event::BeginComponent(name.into_owned(), span),
+			BorrowedEvent::Property(p) => Event::Property(p.into_owned()),
+			BorrowedEvent::EndComponent(name, span) => Event::EndComponent(name.into_owned(), span),
+		}
+	}
+}
+
+//EventReader reads events from the input without building whole components, so that inputs of any size
+// can be processed in constant memory (as long as the individual lines are of reasonable length).
+pub struct EventReader<R: BufRead> {
+	pub(crate) core: ParserCore<'static, ReadLines<R>>,
+}
+
+impl<R> EventReader<BufReader<R>> where R: Read {
+	pub fn from_unbuffered(input: R) -> Self {
+		Self::new(BufReader::new(input))
+	}
+
+	pub fn from_unbuffered_with_options(input: R, options: ParserOptions) -> Self {
+		Self::new_with_options(BufReader::new(input), options)
+	}
+}
+
+impl<R> EventReader<R> where R: BufRead {
+	pub fn new(input: R) -> Self {
+		Self::new_with_options(input, ParserOptions::default())
+	}
+
+	pub fn new_with_options(input: R, options: ParserOptions) -> Self {
+		EventReader {
+			core: ParserCore::new(ReadLines::new(input, options.limits.max_line_length), options),
+		}
+	}
+
+	pub fn options(&self) -> &ParserOptions {
+		&self.core.options
+	}
+
+	//depth returns the number of components which were begun but not yet ended.
+	pub fn depth(&self) -> usize {
+		self.core.open.len()
+	}
+
+	//take_diagnostics returns the diagnostics which were collected so far, see `ParserOptions::collect_diagnostics`.
+	pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+		self.core.take_diagnostics()
+	}
+
+	pub fn next_event(&mut self) -> Result<Option<Event>, Error> {
+		Ok(self.core.next_event()?.map(BorrowedEvent::into_owned))
+	}
+}
+
+impl<R> Iterator for EventReader<R> where R: BufRead {
+	type Item = Result<Event, Error>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.next_event().transpose()
+	}
+}
+
+impl<R> From<R> for EventReader<R> where R: BufRead {
+	fn from(bufread: R) -> Self {
+		EventReader::new(bufread)
+	}
+}
+
+//TreeBuilder assembles components from events.
+pub(crate) struct TreeBuilder<C> {
+	//the components which are not yet complete, the outermost first.
+	stack: Vec<C>,
+}
+
+impl<C> TreeBuilder<C> {
+	pub fn new() -> Self {
+		TreeBuilder { stack: Vec::new() }
+	}
+
+	pub fn clear(&mut self) {
+		self.stack.clear();
+	}
+
+	//truncate drops the innermost incomplete components until only depth of them are left.
+	pub fn truncate(&mut self, depth: usize) {
+		self.stack.truncate(depth);
+	}
+}
+
+impl TreeBuilder<Component> {
+	//push adds the event to the current component and returns the top-level component once it is complete.
+	// raw are the physical lines of BEGIN and END events, if they are kept.
+	pub fn push(&mut self, event: Event, raw: Option<Vec<u8>>) -> Option<Component> {
+		match event {
+			Event::BeginComponent(name, span) => self.stack.push(Component {
+				raw: raw.map(|lines| RawComponent::new(lines, name.as_str())),
+				name,
+				properties: vec![],
+				sub_components: vec![],
+				span: Some(ComponentSpan { begin: span, end: span }),
+				order: vec![],
+			}),
+			Event::Property(p) => self.stack.last_mut()
+					.expect("TreeBuilder::push: property outside of a component")
+					.add_property(p),
+			Event::EndComponent(_, span) => {
+				let mut c = self.stack.pop().expect("TreeBuilder::push: unexpected end of component");
+				if let Some(s) = &mut c.span {
+					s.end = span;
+				}
+				if let Some(r) = &mut c.raw {
+					r.end = raw;
+				}
+				match self.stack.last_mut() {
+					Some(parent) => parent.add_sub_component(c),
+					None => return Some(c),
+				}
+			}
+		}
+		None
+	}
+}
+
+impl<'a> TreeBuilder<BorrowedComponent<'a>> {
+	//push adds the event to the current component and returns the top-level component once it is complete.
+	pub fn push(&mut self, event: BorrowedEvent<'a>, raw: Option<Vec<u8>>) -> Option<BorrowedComponent<'a>> {
+		match event {
+			BorrowedEvent::BeginComponent(name, span) => self.stack.push(BorrowedComponent {
+				raw: raw.map(|lines| RawComponent::new(lines, name.as_str())),
+				name,
+				properties: vec![],
+				sub_components: vec![],
+				span: Some(ComponentSpan { begin: span, end: span }),
+				order: vec![],
+			}),
+			BorrowedEvent::Property(p) => {
+				let parent = self.stack.last_mut().expect("TreeBuilder::push: property outside of a component");
+				parent.order.push(Child::Property(parent.properties.len()));
+				parent.properties.push(p)
+			}
+			BorrowedEvent::EndComponent(_, span) => {
+				let mut c = self.stack.pop().expect("TreeBuilder::push: unexpected end of component");
+				if let Some(s) = &mut c.span {
+					s.end = span;
+				}
+				if let Some(r) = &mut c.raw {
+					r.end = raw;
+				}
+				match self.stack.last_mut() {
+					Some(parent) => {
+						parent.order.push(Child::SubComponent(parent.sub_components.len()));
+						parent.sub_components.push(c)
+					}
+					None => return Some(c),
+				}
+			}
+		}
+		None
+	}
+}
@@ -0,0 +1,380 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+
+use crate::Span;
+use crate::parser::{CstLine, ErrorKind, Limits, ParserOptions};
+use crate::parser::line_lexer::{ItemType, LineLexer};
+use crate::parser::reader::{LineReader, SliceLines};
+
+const BOM: &str = "\u{feff}";
+
+//TokenKind is the kind of a token returned by the Lexer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+	//the UTF-8 byte order mark at the beginning of the input
+	Bom,
+	//the group of a property, which is followed by a Dot
+	Group,
+	Dot,
+	//the property name
+	Name,
+	//the word BEGIN or END of a line beginning or ending a component
+	Begin,
+	End,
+	ComponentName,
+	Semicolon,
+	ParamName,
+	Equals,
+	//a double quote around a parameter value
+	Quote,
+	//a parameter value without quotes. In vCard 2.1, bare parameters (like ";HOME") are a ParamValue too.
+	ParamValue,
+	Comma,
+	Colon,
+	//the property value
+	Value,
+	//the line ending and the space or HTAB which fold a line between two tokens, or a quoted-printable soft
+	// line break (see Dialect::VCard21). A fold within a token is part of the token.
+	Fold,
+	//the line ending (CRLF or LF), which is missing after the last line if the input does not end with one.
+	LineEnding,
+	//the rest of a line, starting at the first character which could not be lexed.
+	Error(ErrorKind),
+}
+
+//Token is a part of a line. The text of a token is unfolded, while its span contains the folds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token<'a> {
+	pub kind: TokenKind,
+	pub text: Cow<'a, str>,
+	pub span: Span,
+	//the spans of the parts of the text if the token is folded, one for every physical line. The folds
+	// between the parts are not included. Empty if the token is not folded.
+	pub fragments: Vec<Span>,
+}
+
+//Lexer splits an input into tokens, which cover every byte of the input. It is independent of the parser
+// and never fails: a line which can not be lexed ends with an Error token covering the rest of the line,
+// and lexing continues with the next line. Only the dialect is taken from the options, lines may always
+// end with LF and no limits apply.
+//
+// The tokens can be read one by one with the Iterator implementation, or line by line with next_line.
+pub struct Lexer<'a> {
+	input: &'a str,
+	reader: LineReader<SliceLines<'a>>,
+	options: ParserOptions,
+	positions: Positions,
+	//the end of the last line which was read
+	covered: usize,
+	//the byte order mark, until it is added to the first line
+	bom: Option<Token<'a>>,
+	pending: VecDeque<CstLine<'a>>,
+	finished: bool,
+	//the tokens of the current line which were not returned by the iterator yet, and the end of the line
+	current: std::vec::IntoIter<Token<'a>>,
+	current_end: usize,
+}
+
+impl<'a> Lexer<'a> {
+	pub fn new(input: &'a str, options: &ParserOptions) -> Self {
+		let options = ParserOptions {
+			lenient_line_endings: true,
+			collect_diagnostics: false,
+			limits: Limits::default(),
+			lossless: false,
+			..options.clone()
+		};
+		let positions = Positions::new(input);
+		let mut bom = None;
+		let mut covered = 0;
+		if input.starts_with(BOM) {
+			bom = Some(positions.token(input, TokenKind::Bom, 0, BOM.len()));
+			covered = BOM.len();
+		}
+		Lexer {
+			input,
+			reader: LineReader::new(SliceLines::new(input.as_bytes()), &options),
+			options,
+			positions,
+			covered,
+			bom,
+			pending: VecDeque::new(),
+			finished: false,
+			current: Vec::new().into_iter(),
+			current_end: 0,
+		}
+	}
+
+	//next_line returns the tokens of the next unfolded line. If the iterator already returned some tokens of
+	// a line, the rest of that line is returned first.
+	pub fn next_line(&mut self) -> Option<CstLine<'a>> {
+		let rest: Vec<_> = self.current.by_ref().collect();
+		if let Some(first) = rest.first() {
+			let span = self.positions.span(first.span.start, self.current_end);
+			return Some(CstLine { tokens: rest, span });
+		}
+		loop {
+			if let Some(line) = self.pending.pop_front() {
+				return Some(line);
+			}
+			if self.finished {
+				return None;
+			}
+			self.read_line();
+		}
+	}
+
+	//read_line reads the next unfolded line and the empty lines before it.
+	fn read_line(&mut self) {
+		let input = self.input;
+		//without limits and with lenient line endings, reading from a slice does not fail.
+		let line = match self.reader.read_unfolded_line() {
+			Ok(Some(line)) => line,
+			_ => {
+				self.empty_lines(self.covered, input.len());
+				//the input only consists of the byte order mark
+				if let Some(bom) = self.bom.take() {
+					self.pending.push_back(CstLine { span: bom.span, tokens: vec![bom] });
+				}
+				self.finished = true;
+				return;
+			}
+		};
+		let line = match line {
+			Cow::Borrowed(b) => Cow::Borrowed(std::str::from_utf8(b).expect("Lexer: line is not valid UTF-8")),
+			Cow::Owned(b) => Cow::Owned(String::from_utf8(b).expect("Lexer: line is not valid UTF-8")),
+		};
+		let start = self.reader.span(0, 0).start;
+		self.empty_lines(self.covered, start);
+		let mut tokens: Vec<Token<'a>> = self.bom.take().into_iter().collect();
+		for (kind, pos, end) in tokenize(&line, &self.options) {
+			let span = self.reader.span(pos, end);
+			if let Some(previous) = tokens.last().filter(|t| t.span.end < span.start) {
+				let fold = self.positions.token(input, TokenKind::Fold, previous.span.end, span.start);
+				tokens.push(fold);
+			}
+			let text = match &line {
+				Cow::Borrowed(line) => Cow::Borrowed(&line[pos..end]),
+				Cow::Owned(line) => Cow::Owned(line[pos..end].to_string()),
+			};
+			let fragments = self.reader.fragments(pos, end);
+			let fragments = if fragments.len() > 1 {
+				fragments.into_iter().map(|(start, end)| self.positions.span(start, end)).collect()
+			} else {
+				Vec::new()
+			};
+			tokens.push(Token { kind, text, span: self.positions.span(span.start, span.end), fragments });
+		}
+		let end = self.reader.next_offset().min(input.len());
+		let last = tokens.last().map_or(start, |t| t.span.end);
+		if last < end {
+			tokens.push(self.positions.token(input, TokenKind::LineEnding, last, end));
+		}
+		let first = tokens.first().map_or(start, |t| t.span.start);
+		let span = self.positions.span(first, end);
+		self.pending.push_back(CstLine { tokens, span });
+		self.covered = end;
+	}
+
+	//empty_lines adds a line for every line ending between start and end, the first one begins with the byte order mark.
+	fn empty_lines(&mut self, mut start: usize, end: usize) {
+		while start < end {
+			let line_end = self.input[start..end].find('\n').map_or(end, |i| start + i + 1);
+			let mut tokens: Vec<Token<'a>> = self.bom.take().into_iter().collect();
+			tokens.push(self.positions.token(self.input, TokenKind::LineEnding, start, line_end));
+			let first = tokens[0].span.start;
+			self.pending.push_back(CstLine { tokens, span: self.positions.span(first, line_end) });
+			start = line_end;
+		}
+	}
+}
+
+impl<'a> Iterator for Lexer<'a> {
+	type Item = Token<'a>;
+
+	fn next(&mut self) -> Option<Token<'a>> {
+		loop {
+			if let Some(token) = self.current.next() {
+				return Some(token);
+			}
+			let line = self.next_line()?;
+			self.current_end = line.span.end;
+			self.current = line.tokens.into_iter();
+		}
+	}
+}
+
+//tokenize returns the kind and range of all tokens of the unfolded line. The line lexer only returns names
+// and values, the delimiters between them are added and quotes are split from parameter values.
+fn tokenize(line: &str, options: &ParserOptions) -> Vec<(TokenKind, usize, usize)> {
+	let mut lexer = LineLexer::new(line, options);
+	let mut out = Vec::new();
+	let mut pos = 0;
+	let mut has_name = false;
+	while let Some(item) = lexer.next_item() {
+		//the name of a bare parameter (vCard 2.1) does not occur in the line
+		if item.synthetic {
+			continue;
+		}
+		//the delimiters before the item. Before an error, the characters which were accepted are part of it.
+		for (i, c) in line[pos..item.pos].char_indices() {
+			let kind = match c {
+				';' => TokenKind::Semicolon,
+				':' => TokenKind::Colon,
+				'=' => TokenKind::Equals,
+				',' => TokenKind::Comma,
+				'.' => TokenKind::Dot,
+				_ => break,
+			};
+			out.push((kind, pos + i, pos + i + 1));
+		}
+		let start = out.last().map_or(0, |&(_, _, end)| end).max(pos);
+		let kind = match item.typ {
+			ItemType::Error(kind) => {
+				out.push((TokenKind::Error(kind), start, line.len()));
+				return out;
+			}
+			ItemType::Id if has_name => TokenKind::ParamName,
+			ItemType::Id => {
+				has_name = true;
+				TokenKind::Name
+			}
+			ItemType::Group => TokenKind::Group,
+			ItemType::Begin => TokenKind::Begin,
+			ItemType::End => TokenKind::End,
+			ItemType::CompName => TokenKind::ComponentName,
+			ItemType::PropValue => TokenKind::Value,
+			ItemType::ParamValue if line[item.pos..item.end].starts_with('"') => {
+				out.push((TokenKind::Quote, item.pos, item.pos + 1));
+				out.push((TokenKind::ParamValue, item.pos + 1, item.end - 1));
+				out.push((TokenKind::Quote, item.end - 1, item.end));
+				pos = item.end;
+				continue;
+			}
+			ItemType::ParamValue => TokenKind::ParamValue,
+		};
+		out.push((kind, item.pos, item.end));
+		pos = item.end;
+	}
+	out
+}
+
+//Positions maps byte offsets of the input to lines and columns. Like in the parser, columns are counted after
+// the byte order mark.
+struct Positions(Vec<usize>);
+
+impl Positions {
+	fn new(input: &str) -> Self {
+		let mut starts = vec![if input.starts_with(BOM) { BOM.len() } else { 0 }];
+		starts.extend(input.bytes().enumerate().filter(|&(_, b)| b == b'\n').map(|(i, _)| i + 1));
+		Positions(starts)
+	}
+
+	//line returns the number of the line containing the offset.
+	fn line(&self, offset: usize) -> usize {
+		self.0.partition_point(|&start| start <= offset).max(1)
+	}
+
+	fn span(&self, start: usize, end: usize) -> Span {
+		let start_line = self.line(start);
+		let end_line = if end > start { self.line(end - 1) } else { start_line };
+		Span { start, end, start_line: start_line as u32, end_line: end_line as u32, column: start.saturating_sub(self.0[start_line - 1]) + 1 }
+	}
+
+	fn token<'a>(&self, input: &'a str, kind: TokenKind, start: usize, end: usize) -> Token<'a> {
+		Token { kind, text: Cow::Borrowed(&input[start..end]), span: self.span(start, end), fragments: Vec::new() }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{ErrorKind, ParserOptions, Span};
+	use crate::parser::lexer::*;
+
+	fn kinds<'s>(tokens: &'s [Token]) -> Vec<(TokenKind, &'s str)> {
+		tokens.iter().map(|t| (t.kind, t.text.as_ref())).collect()
+	}
+
+	fn span(start: usize, end: usize, line: u32, column: usize) -> Span {
+		Span { start, end, start_line: line, end_line: line, column }
+	}
+
+	#[test]
+	fn tokens() {
+		let input = "\u{feff}X;A=b:1\nY:2";
+		let tokens: Vec<_> = Lexer::new(input, &ParserOptions::default()).collect();
+		use TokenKind::*;
+		assert_eq!(kinds(&tokens), vec![
+			(Bom, "\u{feff}"), (Name, "X"), (Semicolon, ";"), (ParamName, "A"), (Equals, "="), (ParamValue, "b"),
+			(Colon, ":"), (Value, "1"), (LineEnding, "\n"), (Name, "Y"), (Colon, ":"), (Value, "2"),
+		]);
+		assert_eq!(tokens[1].span, span(3, 4, 1, 1));
+		assert_eq!(tokens[9].span, span(11, 12, 2, 1));
+		assert!(tokens.iter().all(|t| t.fragments.is_empty()));
+		assert!(Lexer::new("", &ParserOptions::default()).next().is_none());
+	}
+
+	#[test]
+	fn fragments() {
+		let input = "X:ab\r\n c\r\n\td\r\nLONG\r\n NAME:1\r\n";
+		let mut lexer = Lexer::new(input, &ParserOptions::default());
+		let line = lexer.next_line().unwrap();
+		let value = &line.tokens[2];
+		assert_eq!((value.kind, value.text.as_ref()), (TokenKind::Value, "abcd"));
+		assert_eq!((value.span.start, value.span.end, value.span.start_line, value.span.end_line), (2, 12, 1, 3));
+		assert_eq!(value.fragments, vec![span(2, 4, 1, 3), span(7, 8, 2, 2), span(11, 12, 3, 2)]);
+		assert_eq!(&input[line.span.start..line.span.end], "X:ab\r\n c\r\n\td\r\n");
+		//a fold within a name
+		let line = lexer.next_line().unwrap();
+		assert_eq!((line.tokens[0].kind, line.tokens[0].text.as_ref()), (TokenKind::Name, "LONGNAME"));
+		assert_eq!(line.tokens[0].fragments, vec![span(14, 18, 4, 1), span(21, 25, 5, 2)]);
+		assert!(line.tokens[1..].iter().all(|t| t.fragments.is_empty()));
+		assert!(lexer.next_line().is_none());
+	}
+
+	#[test]
+	fn errors() {
+		let input = "X;y:1\r\n:2\r\nY:2\r\n";
+		let lines: Vec<_> = std::iter::from_fn({
+			let mut lexer = Lexer::new(input, &ParserOptions::default());
+			move || lexer.next_line()
+		}).collect();
+		assert_eq!(lines.len(), 3);
+		use TokenKind::*;
+		assert_eq!(kinds(&lines[0].tokens), vec![
+			(Name, "X"), (Semicolon, ";"), (ParamName, "y"), (Error(ErrorKind::ExpectedEquals), ":1"), (LineEnding, "\r\n"),
+		]);
+		assert_eq!(lines[0].tokens[3].span, span(3, 5, 1, 4));
+		assert!(matches!(&lines[1].tokens[..], [Token { kind: Error(_), text, .. }, Token { kind: LineEnding, .. }] if text == ":2"));
+		//the lines after the errors are still lexed
+		assert_eq!(kinds(&lines[2].tokens), vec![(Name, "Y"), (Colon, ":"), (Value, "2"), (LineEnding, "\r\n")]);
+	}
+
+	#[test]
+	fn bare_parameters() {
+		//the bare values equal the names the parameters get in vCard 2.1
+		let input = "X;TYPE;HOME;ENCODING:1";
+		let tokens: Vec<_> = Lexer::new(input, &ParserOptions::vcard21()).collect();
+		use TokenKind::*;
+		assert_eq!(kinds(&tokens), vec![
+			(Name, "X"), (Semicolon, ";"), (ParamValue, "TYPE"), (Semicolon, ";"), (ParamValue, "HOME"),
+			(Semicolon, ";"), (ParamValue, "ENCODING"), (Colon, ":"), (Value, "1"),
+		]);
+		assert!(tokens.windows(2).all(|w| w[0].span.end == w[1].span.start));
+	}
+
+	#[test]
+	fn lines_and_tokens() {
+		let input = "X:1\r\n\r\nY:2\r\n";
+		let mut lexer = Lexer::new(input, &ParserOptions::default());
+		assert_eq!(lexer.next().unwrap().text, "X");
+		//the rest of the current line
+		let rest = lexer.next_line().unwrap();
+		assert_eq!(kinds(&rest.tokens), vec![(TokenKind::Colon, ":"), (TokenKind::Value, "1"), (TokenKind::LineEnding, "\r\n")]);
+		assert_eq!((rest.span.start, rest.span.end), (1, 5));
+		//the empty line
+		let empty = lexer.next_line().unwrap();
+		assert_eq!(kinds(&empty.tokens), vec![(TokenKind::LineEnding, "\r\n")]);
+		assert_eq!(lexer.map(|t| t.kind).collect::<Vec<_>>(), vec![TokenKind::Name, TokenKind::Colon, TokenKind::Value, TokenKind::LineEnding]);
+	}
+}
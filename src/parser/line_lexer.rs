@@ -1,11 +1,15 @@
 use core::fmt;
+use std::borrow::Cow;
+use crate::parser::errors::ErrorKind;
 use crate::parser::line_lexer::State::*;
+use crate::parser::options::{Decoding, Dialect, ParserOptions};
+use crate::parser::vcard21;
 use crate::ALLOWED_PARAMETER_NAME_CHARS;
 use crate::{COMP_BEGIN_S,COMP_END_S};
 
 pub type Pos = usize;
 
-type StateFn = fn(&mut LineLexer) -> State;
+type StateFn = for<'a> fn(&mut LineLexer<'a>) -> State;
 
 enum State {
 	Next(StateFn),
@@ -29,22 +33,38 @@ impl fmt::Debug for State {
 
 
 enum Rune {
-	EOF,
+	Eof,
 	Invalid,
 	Valid(char),
 }
 
 
 #[derive(Clone, Debug)]
-pub struct Item {
+pub struct Item<'a> {
 	pub typ: ItemType,
+	//start and end position of the item in the line, including quotes around parameter values
 	pub pos: Pos,
-	pub val: String,
+	pub end: Pos,
+	pub val: Cow<'a, str>,
+	//the item does not occur in the line, like the name of a bare parameter in vCard 2.1
+	pub synthetic: bool,
 }
 
-impl fmt::Display for Item {
+impl Item<'_> {
+	pub fn into_owned(self) -> Item<'static> {
+		Item {
+			typ: self.typ,
+			pos: self.pos,
+			end: self.end,
+			val: Cow::Owned(self.val.into_owned()),
+			synthetic: self.synthetic,
+		}
+	}
+}
+
+impl fmt::Display for Item<'_> {
 	fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-		if let ItemType::Error = self.typ {
+		if let ItemType::Error(_) = self.typ {
 			write!(f, "{}", self.val)
 		} else if self.val.len() > 10 {
 			write!(f, "{:.10}...", self.val)
@@ -57,13 +77,15 @@ impl fmt::Display for Item {
 #[derive(Clone, Debug, PartialEq)]
 pub enum ItemType {
 	// error occurred; value is text of error
-	Error,
+	Error(ErrorKind),
 	// the value of a property parameter, can contain ^^, ^' or ^n
 	ParamValue,
 	// the value of a property, if the property is of type TEXT, the value can contain \\ , \; , \, , \n or \N
 	PropValue,
 	// the Property/Parameter Name
 	Id,
+	// the group of a property, which precedes the property name (followed by a '.')
+	Group,
 	// an indicator for the start of a component
 	Begin,
 	// an indicator for the end of a component
@@ -73,9 +95,9 @@ pub enum ItemType {
 }
 
 #[derive(Debug)]
-pub struct LineLexer {
+pub struct LineLexer<'a> {
 	//current line
-	pub line: String,
+	pub line: &'a str,
 
 	// current position in the input
 	pos: Pos,
@@ -85,15 +107,22 @@ pub struct LineLexer {
 	width: Pos,
 
 	//next item to be emitted
-	emit: Option<Item>,
+	emit: Option<Item<'a>>,
 
 	//the next state to run
 	state: State,
+
+	dialect: Dialect,
+	//accept U+FFFD, which replaced invalid UTF-8 sequences (Decoding::Lossy)
+	lossy: bool,
+	//the parameter name of the last parameter if it was a bare one (vCard 2.1), so that consecutive bare
+	// parameters become values of the same parameter.
+	bare: Option<&'static str>,
 }
 
-impl LineLexer {
+impl<'a> LineLexer<'a> {
 	// lex creates a new scanner for the input string.
-	pub fn new(input: String) -> Self {
+	pub fn new(input: &'a str, options: &ParserOptions) -> Self {
 		LineLexer {
 			line:input,
 			pos: 0,
@@ -101,12 +130,15 @@ impl LineLexer {
 			width: 0,
 			emit:None,
 			state:Next(lex_prop_name),
+			dialect: options.dialect,
+			lossy: options.decoding == Decoding::Lossy,
+			bare: None,
 		}
 	}
 
 
 
-	pub fn next_item(&mut self) -> Option<Item> {
+	pub fn next_item(&mut self) -> Option<Item<'a>> {
 		while self.emit.is_none(){
 			match self.state {
 				State::Next(sfn) => {
@@ -121,10 +153,10 @@ impl LineLexer {
 	fn next(&mut self) -> Rune {
 		if self.pos >= self.line.len() {
 			self.width = 0;
-			Rune::EOF
+			Rune::Eof
 		} else if self.line.is_char_boundary(self.pos) {
 			let rune = self.line[self.pos..].chars().next().unwrap();
-			if rune == '\u{FFFD}' {
+			if rune == '\u{FFFD}' && !self.lossy {
 				self.width = 0;
 				Rune::Invalid
 			} else {
@@ -191,7 +223,9 @@ impl LineLexer {
 			Item {
 				typ: i,
 				pos: self.start,
-				val: self.line[self.start..self.pos].to_string(),
+				end: self.pos,
+				val: Cow::Borrowed(&self.line[self.start..self.pos]),
+				synthetic: false,
 			}
 		);
 		self.start = self.pos;
@@ -203,19 +237,23 @@ impl LineLexer {
 			Item {
 				typ: i,
 				pos: self.start,
-				val: self.line[self.start..self.pos].trim_matches(matcher).to_string(),
+				end: self.pos,
+				val: Cow::Borrowed(self.line[self.start..self.pos].trim_matches(matcher)),
+				synthetic: false,
 			}
 		);
 		self.start = self.pos;
 	}
 	// errorf returns an error token and terminates the scan by passing
 	// back a Stop that will be the next state, closing the channel.
-	fn errorf(&mut self, errstr: &str) -> State {
+	fn errorf(&mut self, kind: ErrorKind) -> State {
 		self.emit=Some(
 			Item {
-				typ: ItemType::Error,
+				typ: ItemType::Error(kind),
 				pos: self.start,
-				val: errstr.to_string(),
+				end: self.pos,
+				val: Cow::Borrowed(kind.message()),
+				synthetic: false,
 			}
 		);
 		Stop
@@ -227,7 +265,13 @@ impl LineLexer {
 fn lex_prop_name(l: &mut LineLexer) -> State {
 	l.accept_run(ALLOWED_PARAMETER_NAME_CHARS);
 	if l.pos == l.start {
-		return l.errorf("expected one or more alphanumerical characters or '-'");
+		return l.errorf(ErrorKind::InvalidName);
+	}
+	if let Rune::Valid('.') = l.peek() {
+		l.emit(ItemType::Group);
+		l.next();
+		l.ignore();
+		return Next(lex_grouped_prop_name);
 	}
 	if l.line[l.start..l.pos].to_uppercase() == COMP_BEGIN_S {
 		l.emit(ItemType::Begin);
@@ -242,28 +286,38 @@ fn lex_prop_name(l: &mut LineLexer) -> State {
 	Next(lex_before_value)
 }
 
+// lexGroupedPropName scans the property name following a group
+fn lex_grouped_prop_name(l: &mut LineLexer) -> State {
+	l.accept_run(ALLOWED_PARAMETER_NAME_CHARS);
+	if l.pos == l.start {
+		return l.errorf(ErrorKind::InvalidGroupedName);
+	}
+	l.emit(ItemType::Id);
+	Next(lex_before_value)
+}
+
 fn lex_before_comp_name(l: &mut LineLexer) -> State {
 	if l.accept(":") {
 		l.ignore();
 		return Next(lex_comp_name);
 	}
 
-	l.errorf("expected ':'")
+	l.errorf(ErrorKind::ExpectedColon)
 }
 
 fn lex_comp_name(l: &mut LineLexer) -> State {
-	if let Rune::EOF = l.peek() {
-		return l.errorf("component name mustn't have length 0");
+	if let Rune::Eof = l.peek() {
+		return l.errorf(ErrorKind::EmptyComponentName);
 	}
 	l.accept_run(ALLOWED_PARAMETER_NAME_CHARS);
 	match l.peek() {
-		Rune::EOF => {
+		Rune::Eof => {
 			l.emit(ItemType::CompName);
 			Stop
 		}
 		_ => {
 			l.ignore();
-			l.errorf("unexpected character, expected eol, alphanumeric or '-'")
+			l.errorf(ErrorKind::InvalidComponentName)
 		}
 	}
 }
@@ -277,24 +331,45 @@ fn lex_before_value(l: &mut LineLexer) -> State {
 		l.ignore();
 		return Next(lex_param_name);
 	}
-	l.errorf("expected ':' or ';'")
+	l.errorf(ErrorKind::ExpectedColonOrSemicolon)
 }
 
 fn lex_param_name(l: &mut LineLexer) -> State {
 	l.accept_run(ALLOWED_PARAMETER_NAME_CHARS);
 	if l.pos == l.start {
-		return l.errorf("parameter name must not be empty");
+		return l.errorf(ErrorKind::EmptyParameterName);
 	}
+	if l.dialect == Dialect::VCard21 && matches!(l.peek(), Rune::Valid(';') | Rune::Valid(':')) {
+		return lex_bare_param(l);
+	}
+	l.bare = None;
 	l.emit(ItemType::Id);
 	Next(lex_before_param_value)
 }
 
+// lexBareParam emits the name which a bare parameter (vCard 2.1) belongs to, unless the previous parameter
+// was a bare one of the same name, and scans it as value of this parameter.
+fn lex_bare_param(l: &mut LineLexer) -> State {
+	let value = &l.line[l.start..l.pos];
+	let name = if vcard21::ENCODINGS.iter().any(|e| e.eq_ignore_ascii_case(value)) { "ENCODING" } else { "TYPE" };
+	if l.bare != Some(name) {
+		l.emit = Some(Item { typ: ItemType::Id, pos: l.start, end: l.pos, val: Cow::Borrowed(name), synthetic: true });
+	}
+	l.bare = Some(name);
+	Next(lex_bare_param_value)
+}
+
+fn lex_bare_param_value(l: &mut LineLexer) -> State {
+	l.emit(ItemType::ParamValue);
+	Next(lex_after_param_value)
+}
+
 fn lex_before_param_value(l: &mut LineLexer) -> State {
 	if l.accept("=") {
 		l.ignore();
 		return Next(lex_param_value);
 	}
-	l.errorf("expected '='")
+	l.errorf(ErrorKind::ExpectedEquals)
 }
 
 fn lex_param_value(l: &mut LineLexer) -> State {
@@ -314,7 +389,7 @@ fn lex_param_q_value(l: &mut LineLexer) -> State {
 		return Next(lex_after_param_value);
 	}
 
-	l.errorf("expected '\"' or other non-control-characters")
+	l.errorf(ErrorKind::UnterminatedQuotedValue)
 }
 
 fn lex_after_param_value(l: &mut LineLexer) -> State {
@@ -330,14 +405,14 @@ fn lex_after_param_value(l: &mut LineLexer) -> State {
 		l.ignore(); //l.emit(itemComma)
 		return Next(lex_param_value);
 	}
-	l.errorf("expected ',', ':' or ';'")
+	l.errorf(ErrorKind::ExpectedParameterSeparator)
 }
 
 fn lex_value(l: &mut LineLexer) -> State {
 	l.accept_run_unless("");
-	if let Rune::EOF = l.peek() {
+	if let Rune::Eof = l.peek() {
 		l.emit(ItemType::PropValue);
 		return Stop;
 	}
-	l.errorf("unexpected character, expected eol")
+	l.errorf(ErrorKind::InvalidCharacter)
 }
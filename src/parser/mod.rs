@@ -1,115 +1,530 @@
-use core::iter::Peekable;
-use std::io::{BufRead, BufReader, Read, Split};
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Read};
+use std::string::FromUtf8Error;
 
-use crate::{Component, Parameters, Property};
-pub use crate::parser::errors::Error;
+use crate::{BorrowedComponent, BorrowedParameters, BorrowedProperty, Component, Name, ParameterSpan, PropertySpan, Span};
+pub use crate::parser::errors::{Error, ErrorKind};
+pub use crate::parser::event::{BorrowedEvent, Event, EventReader};
+use crate::parser::event::TreeBuilder;
 use crate::parser::line_lexer::{Item, ItemType, LineLexer};
+use crate::parser::reader::{LineReader, LineSource, SliceLines};
 
 mod line_lexer;
+mod cst;
+mod lexer;
+mod charset;
+mod diagnostic;
 mod errors;
+mod event;
+mod options;
+mod path;
+mod reader;
+mod push;
+mod queue;
+#[cfg(feature = "async")]
+mod async_parser;
 pub mod rfc6868;
+pub mod vcard21;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "fancy-errors")]
+mod report;
+
+pub use crate::parser::diagnostic::{Diagnostic, Severity};
+pub use crate::parser::charset::Charset;
+pub use crate::parser::cst::{Cst, CstLine, CstNode};
+pub use crate::parser::lexer::{Lexer, Token, TokenKind};
+pub use crate::parser::options::{Decoding, Dialect, Limit, Limits, ParserOptions};
+pub use crate::parser::path::{ComponentPath, PathSegment};
+pub use crate::parser::push::PushParser;
+pub(crate) use crate::parser::queue::QueueParser;
+#[cfg(feature = "async")]
+pub use crate::parser::async_parser::AsyncParser;
+#[cfg(feature = "rayon")]
+pub use crate::parser::parallel::parse_parallel;
+#[cfg(feature = "fancy-errors")]
+pub use crate::parser::report::Report;
 
 #[cfg(test)]
 mod tests;
 
+//Parser builds whole components from the events read by an EventReader.
 pub struct Parser<R: BufRead> {
-	lexer: Option<LineLexer>,
-	line: (String,u32),
-	next_line: u32,
-	r: Peekable<Split<R>>,
+	events: EventReader<R>,
+	builder: TreeBuilder<Component>,
 }
 
 impl<R> Parser<BufReader<R>> where R: Read {
 	pub fn from_unbuffered(input: R) -> Self {
 		Self::new(BufReader::new(input))
 	}
+
+	pub fn from_unbuffered_with_options(input: R, options: ParserOptions) -> Self {
+		Self::new_with_options(BufReader::new(input), options)
+	}
 }
 
 
 impl<R> Parser<R> where R: BufRead {
 	pub fn new(input: R) -> Self {
+		Self::new_with_options(input, ParserOptions::default())
+	}
+
+	pub fn new_with_options(input: R, options: ParserOptions) -> Self {
 		Parser {
-			lexer: None,
-			line: (String::new(),0),
-			next_line: 1,
-			r: input.split(b'\n').peekable(),
+			events: EventReader::new_with_options(input, options),
+			builder: TreeBuilder::new(),
 		}
 	}
 
+	pub fn options(&self) -> &ParserOptions {
+		self.events.options()
+	}
+
+	//take_diagnostics returns the diagnostics which were collected so far, see `ParserOptions::collect_diagnostics`.
+	pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+		self.events.take_diagnostics()
+	}
 
 	pub fn next_component(&mut self) -> Result<Option<Component>, Error> {
-		self.lexer=None;
-		match self.get_next_item()? {
-			None => Ok(None), //EOF
-			Some(i) => match i.typ {
-				ItemType::Begin => Ok(Some(self.parse_component()?)),
-				ItemType::End | ItemType::Id  => Err(Error::new(i,"expected BEGIN".to_string(),self.line.clone())),
-				_ => unreachable!("parser::next_component: unexpected item type '{:?}' in line {}: {}", i.typ, self.line.1, i.val)
+		self.events.core.reset();
+		self.builder.clear();
+		while let Some(event) = self.events.next_event()? {
+			if let Some(c) = self.builder.push(event, self.events.core.take_raw()) {
+				return Ok(Some(c));
+			}
+		}
+		Ok(None)
+	}
+}
+
+//BorrowedParser parses components from an in-memory input. The parsed components borrow from the input
+// wherever possible and only allocate if the text had to be changed.
+pub struct BorrowedParser<'a> {
+	core: ParserCore<'a, SliceLines<'a>>,
+	builder: TreeBuilder<BorrowedComponent<'a>>,
+}
+
+impl<'a> BorrowedParser<'a> {
+	pub fn new<T: AsRef<[u8]> + ?Sized>(input: &'a T) -> Self {
+		Self::new_with_options(input, ParserOptions::default())
+	}
+
+	pub fn new_with_options<T: AsRef<[u8]> + ?Sized>(input: &'a T, options: ParserOptions) -> Self {
+		BorrowedParser {
+			core: ParserCore::new(SliceLines::new(input.as_ref()), options),
+			builder: TreeBuilder::new(),
+		}
+	}
+
+	pub fn options(&self) -> &ParserOptions {
+		&self.core.options
+	}
+
+	pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+		self.core.take_diagnostics()
+	}
+
+	pub fn next_component(&mut self) -> Result<Option<BorrowedComponent<'a>>, Error> {
+		self.core.reset();
+		self.builder.clear();
+		while let Some(event) = self.core.next_event()? {
+			if let Some(c) = self.builder.push(event, self.core.take_raw()) {
+				return Ok(Some(c));
 			}
 		}
+		Ok(None)
+	}
+
+	//next_event returns the next event without building components, see EventReader.
+	pub fn next_event(&mut self) -> Result<Option<BorrowedEvent<'a>>, Error> {
+		self.core.next_event()
+	}
+}
+
+//ParserCore contains the parsing logic shared by all parsers: it reads one event per unfolded line
+// and checks that components are properly nested.
+pub(crate) struct ParserCore<'a, S> {
+	reader: LineReader<S>,
+	//the remaining items of the current line
+	items: VecDeque<Item<'a>>,
+	line: (Cow<'a, str>, u32),
+	options: ParserOptions,
+	//the components which are currently being parsed, the outermost first.
+	open: Vec<Open<'a>>,
+	//the number of top-level components per (uppercase) name, for the indices of ComponentPath.
+	top_level: HashMap<String, usize>,
+	//the number of components which were begun so far
+	components: usize,
+	//set after a limit was exceeded, no more events are returned afterwards.
+	stopped: bool,
+	//the state of the recovery from an error, while lines are skipped.
+	skip: Option<Skip>,
+	//the problems found so far, if diagnostics are collected instead of returning errors.
+	diagnostics: Option<Vec<Diagnostic>>,
+	//the physical lines of the last BEGIN or END event, see ParserOptions::lossless.
+	raw: Option<Vec<u8>>,
+}
+
+//Open is a component which was begun but not yet ended.
+struct Open<'a> {
+	name: Cow<'a, str>,
+	//the line of the BEGIN line
+	line: u32,
+	//the index among the preceding siblings with the same name
+	index: usize,
+	properties: usize,
+	//the number of sub-components per (uppercase) name
+	children: HashMap<String, usize>,
+}
+
+struct Skip {
+	error: Error,
+	first: u32,
+	//the number of components which were begun and not ended, including the abandoned ones
+	depth: usize,
+}
+
+impl<'a, S> ParserCore<'a, S> where S: LineSource<'a> {
+	pub(crate) fn new(source: S, options: ParserOptions) -> Self {
+		ParserCore {
+			reader: LineReader::new(source, &options),
+			items: VecDeque::new(),
+			line: (Cow::Borrowed(""), 0),
+			diagnostics: if options.collect_diagnostics { Some(Vec::new()) } else { None },
+			options,
+			open: Vec::new(),
+			top_level: HashMap::new(),
+			components: 0,
+			stopped: false,
+			skip: None,
+			raw: None,
+		}
+	}
+
+	//start_at sets the position of the first line for sources which don't start at the beginning of the input,
+	// together with the number of top-level components with each name which precede it.
+	#[cfg(feature = "rayon")]
+	pub(crate) fn start_at(&mut self, line: u32, offset: usize, top_level: HashMap<String, usize>) {
+		self.reader.start_at(line, offset);
+		self.top_level = top_level;
 	}
 
+	//reset forgets all components which were begun but not yet ended.
+	pub(crate) fn reset(&mut self) {
+		self.items.clear();
+		self.open.clear();
+	}
 
-	//parseComponent parses the Component for which itemBegin was already read.
-	fn parse_component(&mut self) -> Result<Component, Error> {
-		let name = match self.get_next_item()? {
-			Some(i) =>
-				if i.typ == ItemType::CompName {
-					i.val
+	//next_event returns the event for the next line. If the source has not yet received a whole line,
+	// None is returned even if this is not the end of the input.
+	pub(crate) fn next_event(&mut self) -> Result<Option<BorrowedEvent<'a>>, Error> {
+		if self.stopped {
+			return Ok(None);
+		}
+		if self.skip.is_some() {
+			return self.continue_recovery();
+		}
+		loop {
+			let res = self.read_event();
+			self.take_reader_problems();
+			match res {
+				//skip the line and continue with the next one
+				Err(e) if self.diagnostics.is_some() && !matches!(e, Error::Io(_) | Error::LimitExceeded { .. }) => {
+					self.items.clear();
+					let span = self.error_span(&e);
+					self.diagnose(Severity::Error, span, e);
+				}
+				res => return self.handle_error(res),
+			}
+		}
+	}
+
+	fn handle_error(&mut self, res: Result<Option<BorrowedEvent<'a>>, Error>) -> Result<Option<BorrowedEvent<'a>>, Error> {
+		match res {
+			Err(e) => {
+				//forget about the rest of the line
+				self.items.clear();
+				let e = e.with_path(self.path());
+				if let Error::LimitExceeded { .. } = e {
+					self.stopped = true;
+					Err(e)
+				} else if self.options.recover_from_errors {
+					self.recover(e)
 				} else {
-					unreachable!("parser::parse_component: unexpected item type '{:?}' in line {}: {}", i.typ, self.line.1, i.val)
-				},
-			None => unreachable!("unexpected EOF in parser::parse_component"),
+					Err(e)
+				}
+			}
+			res => res,
+		}
+	}
+
+	//take_raw returns the physical lines of the last event if it began or ended a component and the lines
+	// are kept. It returns None for END lines which were inserted because they were missing.
+	pub(crate) fn take_raw(&mut self) -> Option<Vec<u8>> {
+		self.raw.take()
+	}
+
+	pub(crate) fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+		self.diagnostics.as_mut().map(std::mem::take).unwrap_or_default()
+	}
+
+	//path returns the path of the components which are currently open.
+	fn path(&self) -> ComponentPath {
+		ComponentPath(self.open.iter()
+				.map(|open| PathSegment { name: open.name.to_string(), index: open.index, line: open.line })
+				.collect())
+	}
+
+	//diagnose records the error as a diagnostic.
+	fn diagnose(&mut self, severity: Severity, span: Span, e: Error) {
+		let e = e.with_path(self.path());
+		if let Some(diagnostics) = &mut self.diagnostics {
+			diagnostics.push(Diagnostic { severity, span, kind: e.kind(), message: e.to_string().trim_end().to_string() });
+		}
+	}
+
+	//take_reader_problems records the line ending problems which were tolerated by the reader as warnings.
+	fn take_reader_problems(&mut self) {
+		let problems = match self.reader.problems.as_mut() {
+			Some(p) if !p.is_empty() => std::mem::take(p),
+			_ => return,
 		};
+		for (e, span) in problems {
+			self.diagnose(Severity::Warning, span, e);
+		}
+	}
 
-		let mut out = Component {
-			name,
-			properties: vec![],
-			sub_components: vec![],
+	//error_span returns the part of the current line an error refers to.
+	fn error_span(&self, e: &Error) -> Span {
+		match e {
+			Error::Malformed { text, pos, line, .. } => self.reader.span(*pos, (pos + text.len()).min(line.len())),
+			Error::Utf8 { error, pos, .. } => {
+				let len = error.utf8_error().error_len().unwrap_or(error.as_bytes().len() - error.utf8_error().valid_up_to());
+				self.reader.span(*pos, pos + len)
+			}
+			_ => self.line_span(),
+		}
+	}
+
+	//is_finished returns whether the end of the input was reached.
+	pub(crate) fn is_finished(&self) -> bool {
+		self.stopped || (self.items.is_empty() && self.skip.is_none() && self.reader.is_finished())
+	}
+
+	//read_event reads the next line and returns the corresponding event.
+	fn read_event(&mut self) -> Result<Option<BorrowedEvent<'a>>, Error> {
+		self.raw = None;
+		let i = match self.get_next_item()? {
+			Some(i) => i,
+			//the rest of the input has not been received yet
+			None if !self.reader.is_finished() => return Ok(None),
+			None => return match self.open.last() {
+				//close the open components one by one
+				Some(open) if self.diagnostics.is_some() => {
+					let e = Error::eof_error(open.name.to_uppercase(), self.line.1);
+					let span = self.reader.end_span();
+					self.diagnose(Severity::Error, span, e);
+					let open = self.open.pop().unwrap();
+					Ok(Some(BorrowedEvent::EndComponent(Name::new(open.name), span)))
+				}
+				Some(open) => Err(Error::eof_error(open.name.to_uppercase(), self.line.1)),
+				None => Ok(None), //EOF
+			}
 		};
-		loop {
-			match self.get_next_item()? {
-				None => return Err(Error::eof_error( out.name,self.line.1)),
-				Some(i) => match i.typ {
-					ItemType::Begin => out.sub_components.push(self.parse_component()?),
-					ItemType::Id => out.properties.push(self.parse_property(i.val)?),
-					ItemType::End => break,
-					_ => unreachable!("unexpected item type in parser::parse_component"),
+		match i.typ {
+			ItemType::Begin => {
+				let name = self.get_comp_name()?.val;
+				let limits = &self.options.limits;
+				if let Some(max) = limits.max_depth.filter(|&max| self.open.len() >= max) {
+					return Err(Error::limit_error(Limit::Depth, max, self.line.1));
+				}
+				if let Some(max) = limits.max_components.filter(|&max| self.components >= max) {
+					return Err(Error::limit_error(Limit::Components, max, self.line.1));
+				}
+				self.components += 1;
+				let siblings = match self.open.last_mut() {
+					Some(parent) => &mut parent.children,
+					None => &mut self.top_level,
+				};
+				let count = siblings.entry(name.to_uppercase()).or_insert(0);
+				let index = *count;
+				*count += 1;
+				self.open.push(Open { name: name.clone(), line: self.line.1, index, properties: 0, children: HashMap::new() });
+				self.raw = self.reader.take_raw();
+				Ok(Some(BorrowedEvent::BeginComponent(Name::new(name), self.line_span())))
+			}
+			ItemType::End if !self.open.is_empty() => {
+				let item = self.get_comp_name()?;
+				let expected = &self.open.last().unwrap().name;
+				if item.val.eq_ignore_ascii_case(expected) {
+					self.open.pop();
+					self.raw = self.reader.take_raw();
+					Ok(Some(BorrowedEvent::EndComponent(Name::new(item.val), self.line_span())))
+				} else {
+					let end = format!("END:{}", expected.to_uppercase());
+					let msg = format!("expected \"{}\"", end);
+					let outer = self.open[..self.open.len() - 1].iter().any(|open| item.val.eq_ignore_ascii_case(&open.name));
+					if !(outer && self.diagnostics.is_some()) {
+						return Err(self.error(ErrorKind::MismatchedEnd, item, Some(msg), vec![end]));
+					}
+					//the END line of the current component is missing: close it and read this line again.
+					let span = self.reader.span(item.pos, item.end);
+					self.diagnose(Severity::Error, span, self.error(ErrorKind::MismatchedEnd, item.clone(), Some(msg), vec![end]));
+					self.items.push_front(item);
+					self.items.push_front(i);
+					let open = self.open.pop().unwrap();
+					Ok(Some(BorrowedEvent::EndComponent(Name::new(open.name), span)))
 				}
 			}
+			ItemType::Id if !self.open.is_empty() => {
+				self.count_property()?;
+				Ok(Some(BorrowedEvent::Property(self.parse_property(None, i)?)))
+			}
+			ItemType::Group if !self.open.is_empty() => {
+				self.count_property()?;
+				let name = match self.get_next_item()? {
+					Some(name) if name.typ == ItemType::Id => name,
+					_ => unreachable!("parser::read_event: expected property name after group in line {}", self.line.1),
+				};
+				Ok(Some(BorrowedEvent::Property(self.parse_property(Some(i), name)?)))
+			}
+			ItemType::End | ItemType::Id | ItemType::Group => Err(self.error(ErrorKind::ExpectedBegin, i, None, vec![])),
+			_ => unreachable!("parser::read_event: unexpected item type '{:?}' in line {}: {}", i.typ, self.line.1, i.val)
 		}
+	}
+
+	//count_property counts a property of the current component and checks the limit.
+	fn count_property(&mut self) -> Result<(), Error> {
+		let count = &mut self.open.last_mut().unwrap().properties;
+		if let Some(max) = self.options.limits.max_properties.filter(|&max| *count >= max) {
+			return Err(Error::limit_error(Limit::Properties, max, self.line.1));
+		}
+		*count += 1;
+		Ok(())
+	}
+
+	//get_comp_name returns the component name following a BEGIN or END item.
+	fn get_comp_name(&mut self) -> Result<Item<'a>, Error> {
 		match self.get_next_item()? {
-			Some(item) => {
-				if item.typ == ItemType::CompName {
-					if item.val == out.name {
-						Ok(out)
-					} else {
-						Err(Error::new(item, format!("expected \"END:{}\"", out.name), self.line.clone()))
-					}
-				} else {
-					unreachable!("unexpected item type in parser::parse_component")
+			Some(i) if i.typ == ItemType::CompName => Ok(i),
+			Some(i) => unreachable!("parser::get_comp_name: unexpected item type '{:?}' in line {}: {}", i.typ, self.line.1, i.val),
+			None => unreachable!("unexpected EOF in parser::get_comp_name"),
+		}
+	}
+
+	//recover skips all lines up to the next line beginning a top-level component, so that the next event
+	// will be its BeginComponent. It returns the error wrapped together with the range of lines which were
+	// skipped, starting at the BEGIN line of the abandoned component. IO errors are not recoverable.
+	fn recover(&mut self, e: Error) -> Result<Option<BorrowedEvent<'a>>, Error> {
+		if let Error::Io(_) = e {
+			return Err(e);
+		}
+		//a mismatched END line was consumed already, it closes the innermost open component (most likely
+		// its name is misspelled). Otherwise a later top-level component could never be reached.
+		let depth = match e.kind() {
+			ErrorKind::MismatchedEnd => self.open.len().saturating_sub(1),
+			_ => self.open.len(),
+		};
+		self.skip = Some(Skip {
+			error: e,
+			first: self.open.first().map_or(self.line.1, |open| open.line),
+			depth,
+		});
+		self.open.clear();
+		self.items.clear();
+		self.continue_recovery()
+	}
+
+	//continue_recovery skips lines until the next top-level component begins and returns the error
+	// afterwards. If the source runs out of lines before that, it returns None and continues on the next call.
+	fn continue_recovery(&mut self) -> Result<Option<BorrowedEvent<'a>>, Error> {
+		let skip = self.skip.as_mut().unwrap();
+		loop {
+			//peek at the next line: if it begins a top-level component, stop skipping. A component which begins
+			// before the abandoned one ended is nested in it, even if it has the same name. IO errors are reported
+			// by the next call.
+			let finished = self.reader.is_finished();
+			match self.reader.peek_line() {
+				Some(Ok(line)) => if skip.depth == 0 && begin_name(line).is_some() {
+					break;
+				},
+				None if !finished => return Ok(None),
+				Some(Err(_)) | None => break,
+			}
+			match self.reader.read_unfolded_line() {
+				Ok(Some(line)) => if begin_name(&line).is_some() {
+					skip.depth += 1;
+				} else if is_end_line(&line) {
+					skip.depth = skip.depth.saturating_sub(1);
+				},
+				Ok(None) if !self.reader.is_finished() => return Ok(None),
+				Ok(None) | Err(Error::Io(_)) => break,
+				//limits still apply while skipping, the original error is dropped.
+				Err(e @ Error::LimitExceeded { .. }) => {
+					self.skip = None;
+					self.stopped = true;
+					return Err(e);
 				}
+				//skip malformed lines as well
+				Err(_) => {}
 			}
-			None => unreachable!("unexpected EOF in parser::parse_component"),
 		}
+		let skip = self.skip.take().unwrap();
+		Err(Error::Recovered { error: Box::new(skip.error), first_line: skip.first, last_line: self.reader.next_line - 1 })
 	}
 
-	//parseProperty parses the next Property while already having parsed the Property name.
-	fn parse_property(&mut self, name: String) -> Result<Property, Error> {
-		let mut out = Property {
-			name,
-			value: "".to_string(),
-			parameters: Parameters::new(),
+	//parseProperty parses the next Property while already having parsed the Property group and name.
+	fn parse_property(&mut self, group: Option<Item<'a>>, name: Item<'a>) -> Result<BorrowedProperty<'a>, Error> {
+		let mut span = PropertySpan {
+			line: self.line_span(),
+			group: group.as_ref().map(|g| self.reader.span(g.pos, g.end)),
+			name: self.reader.span(name.pos, name.end),
+			..PropertySpan::default()
+		};
+		let mut out = BorrowedProperty {
+			group: group.map(|g| Name::new(g.val)),
+			name: Name::new(name.val),
+			value: Cow::Borrowed(""),
+			parameters: BorrowedParameters::new(),
 			old_line: Some(self.line.clone()),
+			span: None,
+			raw: self.reader.take_raw(),
 		};
-		let mut last_param_name = "".to_string();
+		//the parameter which is currently being parsed, it is added once all of its values are known.
+		let mut param: Option<(Cow<'a, str>, Vec<Cow<'a, str>>)> = None;
+		//the value of the CHARSET parameter, for errors
+		let mut charset = None;
 		loop {
 			match self.get_next_item()? {
 				Some(item) => match item.typ {
-					ItemType::Id => last_param_name = item.val,
-					ItemType::ParamValue => out.add_param(last_param_name.clone(), item.val).unwrap(),
+					//every parameter name starts a new entry, even if the parameter occurred before.
+					ItemType::Id => {
+						if let Some((name, values)) = param.take() {
+							out.parameters.push(name, values);
+						}
+						span.parameters.push(ParameterSpan { name: self.reader.span(item.pos, item.end), values: vec![] });
+						param = Some((item.val, vec![]));
+					}
+					ItemType::ParamValue => {
+						span.parameters.last_mut().unwrap().values.push(self.reader.span(item.pos, item.end));
+						let (name, values) = param.as_mut().unwrap();
+						if charset.is_none() && name.eq_ignore_ascii_case("CHARSET") {
+							charset = Some(item.clone());
+						}
+						values.push(item.val);
+					}
 					ItemType::PropValue => {
+						if let Some((name, values)) = param.take() {
+							out.parameters.push(name, values);
+						}
+						span.value = self.reader.span(item.pos, item.end);
 						out.value = item.val;
+						if self.options.dialect == Dialect::VCard21 {
+							self.decode_vcard21(&mut out, &mut span, charset, item.pos)?;
+						}
+						out.span = Some(span);
 						return Ok(out);
 					}
 					_ => unreachable!("unexpected item type in parser::parse_property"),
@@ -119,95 +534,181 @@ impl<R> Parser<R> where R: BufRead {
 		}
 	}
 
-	//getNextItem returns the next lexer item, feeding (unfolded) lines into the lexer if neccessary.
-	// It also converts identifiers (itemCompName, itemID) into upper case, errors encountered by the
-	// lexer into 'error' values and property parameter values into their original value (without escaped characters).
-	fn get_next_item(&mut self) -> Result<Option<Item>, Error> {
-		if self.lexer.is_none() {
-			self.line.1 = self.next_line;
-			if let Some(line) = self.read_unfolded_line()? {
-				self.line.0 = String::from_utf8(line)?;
-				self.lexer = Some(LineLexer::new(self.line.0.clone()));
-			} else {
-				//Reached EOF
-				return Ok(None);
+	//decode_vcard21 decodes a quoted-printable value and the character set of the value (see Dialect::VCard21)
+	// and removes the ENCODING and CHARSET parameters (and their spans) which declared them.
+	fn decode_vcard21(&self, out: &mut BorrowedProperty<'a>, span: &mut PropertySpan, charset: Option<Item<'a>>, value_pos: usize) -> Result<(), Error> {
+		let quoted_printable = out.parameters.get_all("ENCODING").any(|e| e.eq_ignore_ascii_case("QUOTED-PRINTABLE"));
+		let decoder = match &charset {
+			Some(item) => match Charset::from_label(&item.val) {
+				Some(decoder) => decoder,
+				None => return Err(self.error(ErrorKind::UnsupportedCharset, item.clone(), None, vec![])),
+			},
+			None => Charset::Utf8,
+		};
+		if quoted_printable {
+			let bytes = vcard21::decode_quoted_printable(&out.value);
+			out.value = Cow::Owned(match decoder.decode(bytes) {
+				Ok(value) => value,
+				//without a declared character set, the value is most likely from a legacy Windows system.
+				Err(e) if charset.is_none() => Charset::Windows1252.decode(e.into_bytes()).unwrap(),
+				//the position in the decoded value can not be mapped to the line.
+				Err(e) => return Err(self.utf8_error(e, value_pos)),
+			});
+		} else if charset.is_none() {
+			return Ok(());
+		}
+		//otherwise, the line was already converted to UTF-8 before it was lexed.
+		let mut spans = std::mem::take(&mut span.parameters).into_iter();
+		for (name, values) in std::mem::take(&mut out.parameters) {
+			let param_span = spans.next();
+			if !(name.as_str().eq_ignore_ascii_case("CHARSET") || (quoted_printable && name.as_str().eq_ignore_ascii_case("ENCODING"))) {
+				out.parameters.push(name, values);
+				span.parameters.extend(param_span);
 			}
 		}
+		Ok(())
+	}
 
-		let mut i;
-		match self.lexer.as_mut().unwrap().next_item() {
-			None => {
-				//unexpected Tokenstream EOF, should not happen (because lexer throws an error because that happens)
-				unreachable!("unexpected token stream EOF in parser::get_next_item");
-				//self.l=None;
-				//return Ok(None);
+	//getNextItem returns the next lexer item, feeding (unfolded) lines into the lexer if neccessary.
+	// It also converts errors encountered by the lexer into 'error' values and property parameter values
+	// into their original value (without escaped characters).
+	fn get_next_item(&mut self) -> Result<Option<Item<'a>>, Error> {
+		//lines which are not valid UTF-8 are skipped if diagnostics are collected.
+		while self.items.is_empty() {
+			let line = match self.reader.read_unfolded_line()? {
+				Some(line) => match self.decode(line)? {
+					Some(line) => line,
+					None => continue,
+				},
+				//Reached EOF
+				None => {
+					self.line.1 = self.reader.line;
+					return Ok(None);
+				}
+			};
+			//lex the whole line at once, items can only borrow from the input if the line does.
+			match &line {
+				Cow::Borrowed(l) => {
+					let mut lexer = LineLexer::new(l, &self.options);
+					while let Some(item) = lexer.next_item() {
+						self.items.push_back(item);
+					}
+				}
+				Cow::Owned(l) => {
+					let mut lexer = LineLexer::new(l, &self.options);
+					while let Some(item) = lexer.next_item() {
+						self.items.push_back(item.into_owned());
+					}
+				}
 			}
-			Some(it) => i = it,
+			self.line = (line, self.reader.line);
 		}
 
+		//the lexer always ends a line with an item of type CompName, PropValue or Error.
+		let mut i = self.items.pop_front()
+				.expect("unexpected token stream EOF in parser::get_next_item");
+
 		match i.typ {
-			ItemType::Error => {
-				return Err(Error::new(i,  "".to_string(), self.line.clone()));
-			}
-			ItemType::CompName => {
-				i.val = i.val.to_uppercase();
-				self.lexer = None;
+			ItemType::Error(kind) => {
+				self.items.clear();
+				//the error refers to the character at its position
+				let len = self.line.0[i.pos..].chars().next().map_or(0, |c| c.len_utf8());
+				let text = Item { val: Cow::Owned(self.line.0[i.pos..i.pos + len].to_string()), ..i };
+				return Err(self.error(kind, text, None, vec![]));
 			}
-			ItemType::Id => i.val = i.val.to_uppercase(),
-			ItemType::PropValue => self.lexer = None,
-			ItemType::ParamValue => i.val = rfc6868::unescape_param_value(&i.val),
+			ItemType::ParamValue if i.val.contains('^') => i.val = Cow::Owned(rfc6868::unescape_param_value(&i.val)),
 			_ => {}
 		}
 		Ok(Some(i))
 	}
 
-	fn read_unfolded_line(&mut self) -> Result<Option<Vec<u8>>, Error> {
-		let mut buf;
-
-		match self.r.next() {
-			None => return Ok(None), //Reached EOF
-			Some(line) => buf = line?,
-		}
-		// increment line counter
-		self.next_line += 1;
-		// all lines have to end with a \r\n. Empty lines without a \r\n are also not allowed. empty lines at the end return EOF (represented as Ok(None)
-		if let Some(byte) = buf.pop() {
-			if byte != b'\r' {
-				buf.push(byte);
-				return Err(Error::crlf_error(buf, self.line.1, self.r.peek().is_some()));
+	//decode converts the line to UTF-8 according to ParserOptions::decoding. If the line is not valid UTF-8
+	// and diagnostics are collected, the problem is recorded and None is returned, so that the line is skipped.
+	fn decode(&mut self, line: Cow<'a, [u8]>) -> Result<Option<Cow<'a, str>>, Error> {
+		//lines with values in another character set are converted before they are lexed.
+		if self.options.dialect == Dialect::VCard21 {
+			if let Some(decoded) = vcard21::decode_line(&line) {
+				return Ok(Some(Cow::Owned(decoded)));
 			}
-		} else if self.r.peek().is_some() {
-			//there are some lines following and this line contains only "\n" => is not allowed!
-			//buf is empty (otherwise a pop() would have given Some(_), not None
-			return Err(Error::crlf_error( buf, self.line.1, self.r.peek().is_some()));
-		} else {
-
-			//this is the last line (after \r\n) and it is empty.
-			return Ok(None);
 		}
-
-		// peek at next line. If next line begins with a space or HTAB (\t), 'unfold' it.
-		// if it would throw an error, don't return it (it's only borrowed), force a recursive call which will also trigger it.
-		// if there is nothing to read or the next line begins with another character, don't unfold.
-		let append = match self.r.peek() {
-			Some(x) => match x {
-				Err(_) => true,
-				Ok(next_line) if !next_line.is_empty() => next_line[0] == b' ' || next_line[0] == b'\t',
-				//there is a next line but it is empty.
-				_ =>false,
+		let bytes = match line {
+			Cow::Borrowed(line) => match std::str::from_utf8(line) {
+				Ok(line) => return Ok(Some(Cow::Borrowed(line))),
+				//only allocate in the error case to get the same error type as an owned line.
+				Err(_) => line.to_vec(),
 			},
-			None => false,
+			Cow::Owned(line) => line,
+		};
+		let e = match String::from_utf8(bytes) {
+			Ok(line) => return Ok(Some(Cow::Owned(line))),
+			Err(e) => e,
 		};
+		let decoded = match self.options.decoding {
+			Decoding::Strict => None,
+			Decoding::Lossy => Some(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+			Decoding::Fallback(fallback) => charset::declared_charset(e.as_bytes())
+					.and_then(|declared| declared.decode(e.as_bytes().to_vec()).ok())
+					.or_else(|| fallback.decode(e.as_bytes().to_vec()).ok()),
+		};
+		let pos = e.utf8_error().valid_up_to();
+		let e = self.utf8_error(e, pos);
+		let span = self.error_span(&e);
+		match decoded {
+			Some(line) => {
+				self.diagnose(Severity::Warning, span, e);
+				Ok(Some(Cow::Owned(line)))
+			}
+			None if self.diagnostics.is_some() => {
+				self.diagnose(Severity::Error, span, e);
+				Ok(None)
+			}
+			None => Err(e),
+		}
+	}
+
+	//utf8_error returns the error for the invalid sequence of e, which was found at pos in the current line.
+	fn utf8_error(&self, e: FromUtf8Error, pos: usize) -> Error {
+		let offset = self.reader.span(pos, pos).start;
+		Error::utf8_error(e, self.reader.line, pos, offset)
+	}
+
+	//line_span returns the span of the current unfolded line, without the line ending.
+	fn line_span(&self) -> Span {
+		self.reader.span(0, self.line.0.len())
+	}
 
-		if append {
-			let next = self.read_unfolded_line()?.unwrap();
-			//remove space/htab at the front and append
-			buf.extend_from_slice(&next[1..]);
+	//error returns an error for the text of the item. The message and expected tokens of the kind are used
+	// unless others are given.
+	fn error(&self, kind: ErrorKind, i: Item, msg: Option<String>, expected: Vec<String>) -> Error {
+		Error::Malformed {
+			kind,
+			message: msg.unwrap_or_else(|| kind.message().to_string()),
+			text: i.val.into_owned(),
+			expected: if expected.is_empty() { kind.expected().iter().map(|s| s.to_string()).collect() } else { expected },
+			pos: i.pos,
+			line: self.line.0.to_string(),
+			line_number: self.line.1,
+			path: ComponentPath::default(),
 		}
-		Ok(Some(buf))
 	}
 }
 
+//begin_name returns the component name if the given line begins a component.
+pub(crate) fn begin_name(line: &[u8]) -> Option<&[u8]> {
+	let prefix = b"BEGIN:";
+	if line.len() >= prefix.len() && line[..prefix.len()].eq_ignore_ascii_case(prefix) {
+		let name = &line[prefix.len()..];
+		Some(name.strip_suffix(b"\r").unwrap_or(name))
+	} else {
+		None
+	}
+}
+
+pub(crate) fn is_end_line(line: &[u8]) -> bool {
+	let prefix = b"END:";
+	line.len() >= prefix.len() && line[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
 impl<R> Iterator for Parser<R>
 	where R: BufRead {
 	type Item = Result<Component, Error>;
@@ -221,6 +722,26 @@ impl<R> Iterator for Parser<R>
 	}
 }
 
+impl<'a> Iterator for BorrowedParser<'a> {
+	type Item = Result<BorrowedComponent<'a>, Error>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.next_component().transpose()
+	}
+}
+
+impl<'a> From<&'a str> for BorrowedParser<'a> {
+	fn from(input: &'a str) -> Self {
+		BorrowedParser::new(input)
+	}
+}
+
+impl<'a> From<&'a [u8]> for BorrowedParser<'a> {
+	fn from(input: &'a [u8]) -> Self {
+		BorrowedParser::new(input)
+	}
+}
+
 impl<R> From<R> for Parser<R>
 	where R: BufRead {
 	fn from(bufread: R) -> Self {
@@ -0,0 +1,127 @@
+use core::fmt;
+
+use crate::parser::charset::Charset;
+
+//ParserOptions configures how strictly a Parser interprets its input.
+// The default options follow the RFCs to the letter.
+#[derive(Clone, Debug, Default)]
+pub struct ParserOptions {
+	// Accept lines terminated only by LF ('\n'), files mixing CRLF and LF line endings, a missing line
+	// terminator after the last line and empty lines between content lines (which are skipped).
+	// If false, every line has to end with CRLF ("\r\n") as required by RFC 5545 and RFC 6350.
+	pub lenient_line_endings: bool,
+	// After an error, skip all lines up to the beginning of the next top-level component, so that the
+	// next call to `Parser::next_component` (or `Iterator::next`) continues with it. The returned error is
+	// then an `Error::Recovered` which contains the original error and the range of skipped lines.
+	// IO errors can not be recovered from.
+	pub recover_from_errors: bool,
+	// Instead of returning errors, record them as diagnostics and continue with the next line. Components
+	// are repaired as well as possible: malformed lines and lines which are not valid UTF-8 are skipped,
+	// missing END lines are inserted and the end of the input closes the open components. The diagnostics
+	// are returned by `take_diagnostics`. IO errors and exceeded limits are still returned as errors.
+	pub collect_diagnostics: bool,
+	// Limits on the size of the input, which should be set when parsing untrusted input.
+	pub limits: Limits,
+	// The dialect of the input, see Dialect.
+	pub dialect: Dialect,
+	// How lines which are not valid UTF-8 are decoded, see Decoding.
+	pub decoding: Decoding,
+	// Keep the physical lines every property and component was parsed from (in `Property::raw` and
+	// `Component::raw`), so that an encoder with `EncoderOptions::lossless` writes unmodified ones verbatim.
+	pub lossless: bool,
+}
+
+impl ParserOptions {
+	//strict returns the default options, which only accept input conforming to the RFCs.
+	pub fn strict() -> Self {
+		Self::default()
+	}
+
+	//lenient returns options which accept the line endings of files generated by non-conforming tools.
+	pub fn lenient() -> Self {
+		ParserOptions {
+			lenient_line_endings: true,
+			..Self::default()
+		}
+	}
+
+	//vcard21 returns options for reading vCard 2.1 files, which are often written with LF line endings as well.
+	pub fn vcard21() -> Self {
+		ParserOptions {
+			lenient_line_endings: true,
+			dialect: Dialect::VCard21,
+			..Self::default()
+		}
+	}
+}
+
+//Decoding selects how lines which are not valid UTF-8 are handled. A UTF-8 byte order mark at the
+// beginning of the input is always skipped. If diagnostics are collected, every line which was decoded
+// with a replacement or fallback is recorded as a warning.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Decoding {
+	//return an `Error::Utf8` with the line and the byte offset of the first invalid sequence
+	#[default]
+	Strict,
+	//replace invalid sequences with U+FFFD (the replacement character)
+	Lossy,
+	//decode the whole line with the character set declared by its CHARSET parameter or, if it does not
+	// declare a supported one, with the given character set
+	Fallback(Charset),
+}
+
+//Dialect selects the syntax of content lines which is read or written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dialect {
+	//the syntax of RFC 5545 and RFC 6350
+	#[default]
+	Standard,
+	//the syntax of vCard 2.1, which predates the RFCs: parameters may consist of only a value (like
+	// "TEL;HOME;VOICE:..."), which is a value of TYPE (or of ENCODING for "QUOTED-PRINTABLE", "BASE64", "7BIT"
+	// and "8BIT"), and values may be encoded with ENCODING=QUOTED-PRINTABLE, whose lines end with '=' if
+	// they continue on the next line. The parser decodes these values and values in the character set
+	// declared with CHARSET into UTF-8 (quoted-printable values without CHARSET which are not valid UTF-8 are
+	// decoded as WINDOWS-1252) and removes both parameters, the encoder writes bare TYPE
+	// parameters and encodes values with line breaks or non-ASCII characters as quoted-printable UTF-8.
+	VCard21,
+}
+
+//Limits restricts the resources a parser may use. If a limit is exceeded, the parser returns an
+// `Error::LimitExceeded` and stops, even if it was configured to recover from errors.
+// None means that there is no limit, which is the default for all limits.
+#[derive(Clone, Debug, Default)]
+pub struct Limits {
+	// The maximum number of components which can be nested into each other, a component without
+	// sub-components has a depth of 1.
+	pub max_depth: Option<usize>,
+	// The maximum length of an unfolded line in bytes, without the line ending.
+	pub max_line_length: Option<usize>,
+	// The maximum number of properties in a single component (not counting those of its sub-components).
+	pub max_properties: Option<usize>,
+	// The maximum number of components in the whole input, nested components are counted as well.
+	pub max_components: Option<usize>,
+	// The maximum number of bytes which are read from the input.
+	pub max_total_bytes: Option<usize>,
+}
+
+//Limit identifies one of the Limits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+	Depth,
+	LineLength,
+	Properties,
+	Components,
+	TotalBytes,
+}
+
+impl fmt::Display for Limit {
+	fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+		match self {
+			Limit::Depth => write!(f, "nesting depth of components"),
+			Limit::LineLength => write!(f, "length of an unfolded line in bytes"),
+			Limit::Properties => write!(f, "number of properties in a component"),
+			Limit::Components => write!(f, "number of components"),
+			Limit::TotalBytes => write!(f, "size of the input in bytes"),
+		}
+	}
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use rayon::prelude::*;
+
+use crate::Component;
+use crate::parser::{begin_name, is_end_line, Error, Limits, Parser, ParserOptions};
+use crate::parser::reader::{LineReader, SliceLines};
+
+//Chunk is a part of the input which begins with a top-level component (or is the part before the first one).
+#[derive(Debug, PartialEq)]
+struct Chunk {
+	start: usize,
+	end: usize,
+	//the number of the first line
+	line: u32,
+	//the uppercase name of the top-level component and the number of preceding top-level components with this name
+	index: Option<(String, usize)>,
+}
+
+//parse_parallel parses all components of the input on the rayon thread pool, which is useful for large
+// files containing many top-level components (like a vCard dump). The input is split at the BEGIN lines
+// of the top-level components, which are then parsed independently by a Parser each. The results are
+// returned in the order of the input and errors contain the line numbers and paths in the whole input.
+// Unlike with a single Parser, an error never affects the following top-level component. The limits
+// (except for max_total_bytes) apply to every top-level component separately and diagnostics are dropped.
+pub fn parse_parallel(input: &[u8], options: &ParserOptions) -> Vec<Result<Component, Error>> {
+	if let Some(max) = options.limits.max_total_bytes.filter(|&max| input.len() > max) {
+		return vec![Err(Error::limit_error(crate::Limit::TotalBytes, max, 1))];
+	}
+	//the limit is checked above, a chunk could never exceed it otherwise
+	let mut options = options.clone();
+	options.limits.max_total_bytes = None;
+	split(input, &options).into_par_iter()
+			.map(|chunk| {
+				let mut parser = Parser::new_with_options(&input[chunk.start..chunk.end], options.clone());
+				parser.events.core.start_at(chunk.line, chunk.start, chunk.index.into_iter().collect::<HashMap<_, _>>());
+				parser.collect::<Vec<_>>()
+			})
+			.collect::<Vec<_>>()
+			.into_iter()
+			.flatten()
+			.collect()
+}
+
+//split splits the input before every line beginning a top-level component. The lines are read like by the
+// Parser, so folded BEGIN and END lines are found and (in vCard 2.1) soft line breaks are followed. If an
+// END line is missing, the following components are left to the Parser of the chunk, which may recover
+// from the error.
+fn split(input: &[u8], options: &ParserOptions) -> Vec<Chunk> {
+	//only the boundaries are needed here, errors are found by the parsers of the chunks.
+	let options = ParserOptions {
+		lenient_line_endings: true,
+		collect_diagnostics: false,
+		limits: Limits::default(),
+		lossless: false,
+		..options.clone()
+	};
+	let mut reader = LineReader::new(SliceLines::new(input), &options);
+	let mut chunks = vec![Chunk { start: 0, end: 0, line: 1, index: None }];
+	let mut counts: HashMap<String, usize> = HashMap::new();
+	let mut depth = 0usize;
+	while let Ok(Some(l)) = reader.read_unfolded_line() {
+		if let Some(name) = begin_name(&l) {
+			if depth == 0 {
+				//the first chunk includes the byte order mark
+				let start = if reader.line == 1 { 0 } else { reader.span(0, 0).start };
+				let key = String::from_utf8_lossy(name).to_uppercase();
+				let count = counts.entry(key.clone()).or_insert(0);
+				chunks.last_mut().unwrap().end = start;
+				chunks.push(Chunk { start, end: start, line: reader.line, index: Some((key, *count)) });
+				*count += 1;
+			}
+			depth += 1;
+		} else if is_end_line(&l) {
+			depth = depth.saturating_sub(1);
+		}
+	}
+	chunks.last_mut().unwrap().end = input.len();
+	//the part before the first component is usually empty
+	if chunks[0].start == chunks[0].end {
+		chunks.remove(0);
+	}
+	chunks
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+
+	use crate::{ErrorKind, Parser, ParserOptions};
+	use crate::parser::parallel::*;
+	use crate::test_helper::{assert_comp_equal, c, c2, expect_err, p2};
+
+	#[test]
+	fn chunks() {
+		let input = b"BEGIN:a\r\nBEGIN:a\r\nEND:a\r\nEND:a\r\nBEGIN:b\r\nX:1\r\nEND:b\r\nbegin:A\r\nEND:a\r\n";
+		let chunks = split(input, &ParserOptions::default());
+		assert_eq!(chunks.iter().map(|c| (c.start, c.line, c.index.clone())).collect::<Vec<_>>(), vec![
+			(0, 1, Some(("A".to_string(), 0))),
+			(32, 5, Some(("B".to_string(), 0))),
+			(53, 8, Some(("A".to_string(), 1))),
+		]);
+		assert_eq!(chunks[2].end, input.len());
+		assert_eq!(split(b"X:1\nBEGIN:a\n", &ParserOptions::default())[0], Chunk { start: 0, end: 4, line: 1, index: None });
+		assert!(split(b"", &ParserOptions::default()).is_empty());
+	}
+
+	#[test]
+	fn same_as_parser() {
+		let input = "\u{feff}BEGIN:VCARD\r\nFN:a\r\n b\r\nEND:VCARD\r\nBEGIN:VCARD\r\nBEGIN:X\r\nEND:X\r\nEND:VCARD\r\nBEGIN:VEVENT\r\nEND:VEVENT\r\n";
+		let got = parse_parallel(input.as_bytes(), &ParserOptions::default());
+		let expected: Vec<_> = Parser::new(Cursor::new(input)).collect();
+		assert_eq!(got.len(), 3);
+		for (got, expected) in got.iter().zip(&expected) {
+			assert_comp_equal(got.as_ref().unwrap(), expected.as_ref().unwrap());
+			assert_eq!(got.as_ref().unwrap().span, expected.as_ref().unwrap().span);
+		}
+		assert_comp_equal(got[0].as_ref().unwrap(), &c("VCARD", vec![p2("FN", "ab")], vec![]));
+		assert_comp_equal(got[2].as_ref().unwrap(), &c2("VEVENT"));
+	}
+
+	#[test]
+	fn folded_lines() {
+		//a soft line break continued with "BEGIN:" and folded BEGIN and END lines
+		let input = "BEGIN:VCARD\r\nNOTE;QUOTED-PRINTABLE:a=\r\nBEGIN:x\r\nEND:VCARD\r\nBEG\r\n IN:VCARD\r\nFN:b\r\nEN\r\n D:VCARD\r\nBEGIN:VCARD\r\nEND:VCARD\r\n";
+		let options = ParserOptions::vcard21();
+		let chunks = split(input.as_bytes(), &options);
+		assert_eq!(chunks.iter().map(|c| (c.start, c.line)).collect::<Vec<_>>(), vec![(0, 1), (59, 5), (95, 10)]);
+		let got = parse_parallel(input.as_bytes(), &options);
+		let expected: Vec<_> = Parser::new_with_options(Cursor::new(input), options).collect();
+		assert_eq!(got.len(), 3);
+		for (got, expected) in got.iter().zip(&expected) {
+			assert_comp_equal(got.as_ref().unwrap(), expected.as_ref().unwrap());
+			assert_eq!(got.as_ref().unwrap().span, expected.as_ref().unwrap().span);
+		}
+		assert_eq!(got[0].as_ref().unwrap().properties[0].value, "aBEGIN:x");
+	}
+
+	#[test]
+	fn errors() {
+		let input = b"BEGIN:a\r\nEND:a\r\nBEGIN:a\r\nX;y:1\r\nEND:a\r\nBEGIN:b\r\nEND:b\r\nBEGIN:a\r\n";
+		let got = parse_parallel(input, &ParserOptions::default());
+		//like with a single Parser, the END line of the component with the error is unexpected
+		assert_eq!(got.len(), 5);
+		let e = got[1].as_ref().unwrap_err();
+		assert_eq!((e.kind(), e.line()), (ErrorKind::ExpectedEquals, Some(4)));
+		assert_eq!(e.path().unwrap().to_string(), "a[1]");
+		assert_eq!(got[2].as_ref().unwrap_err().kind(), ErrorKind::ExpectedBegin);
+		assert_comp_equal(got[3].as_ref().unwrap(), &c2("b"));
+		expect_err(got[4].as_ref(), "line 9: \tunexpected end of file or stream, expected END:A\n\tin a[2] (BEGIN in line 8)");
+	}
+}
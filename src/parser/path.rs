@@ -0,0 +1,43 @@
+use core::fmt;
+use core::ops::Deref;
+
+//ComponentPath is the stack of components which were open when an error occurred, the outermost first.
+// It is displayed like "VCALENDAR[0]/VEVENT[41]/VALARM[0]".
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ComponentPath(pub(crate) Box<[PathSegment]>);
+
+//PathSegment identifies a component by its name and its index among the preceding components with the
+// same name in its parent component (or in the input, for top-level components).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathSegment {
+	pub name: String,
+	pub index: usize,
+	//the line of the BEGIN line of the component
+	pub line: u32,
+}
+
+impl Deref for ComponentPath {
+	type Target = [PathSegment];
+
+	fn deref(&self) -> &[PathSegment] {
+		&self.0
+	}
+}
+
+impl fmt::Display for PathSegment {
+	fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+		write!(f, "{}[{}]", self.name, self.index)
+	}
+}
+
+impl fmt::Display for ComponentPath {
+	fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+		for (i, segment) in self.0.iter().enumerate() {
+			if i > 0 {
+				f.write_str("/")?;
+			}
+			segment.fmt(f)?;
+		}
+		Ok(())
+	}
+}
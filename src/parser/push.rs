@@ -0,0 +1,96 @@
+use crate::Component;
+use crate::parser::{Diagnostic, Error, Event, ParserOptions, QueueParser};
+
+//PushParser parses components from chunks of bytes which are fed into it as they arrive, e.g. from a
+// message queue. Chunks may end anywhere, even inside of a line ending or a UTF-8 sequence. A line is parsed
+// as soon as the first byte of the next line shows that it is not continued there, so the components
+// (or events) become available as soon as possible, the last line of the input after `finish` was called.
+// The lines are unfolded and lexed like by the other parsers.
+pub struct PushParser {
+	parser: QueueParser,
+	//the part of the current physical line which was fed already
+	line: Vec<u8>,
+	finished: bool,
+}
+
+impl PushParser {
+	pub fn new() -> Self {
+		Self::new_with_options(ParserOptions::default())
+	}
+
+	pub fn new_with_options(options: ParserOptions) -> Self {
+		PushParser {
+			parser: QueueParser::new(options),
+			line: Vec::new(),
+			finished: false,
+		}
+	}
+
+	pub fn options(&self) -> &ParserOptions {
+		self.parser.options()
+	}
+
+	//depth returns the number of components which were begun but not yet ended.
+	pub fn depth(&self) -> usize {
+		self.parser.depth()
+	}
+
+	//take_diagnostics returns the diagnostics which were collected so far, see `ParserOptions::collect_diagnostics`.
+	pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+		self.parser.take_diagnostics()
+	}
+
+	//feed adds the next chunk of the input. It panics if the end of the input was marked with `finish` already.
+	pub fn feed(&mut self, mut chunk: &[u8]) {
+		assert!(!self.finished, "PushParser::feed: called after finish");
+		while let Some(i) = chunk.iter().position(|&b| b == b'\n') {
+			self.line.extend_from_slice(&chunk[..i]);
+			self.parser.push_line(std::mem::take(&mut self.line));
+			chunk = &chunk[i + 1..];
+		}
+		self.line.extend_from_slice(chunk);
+		//don't wait for the end of a line which is too long anyway, the parser rejects it.
+		if self.options().limits.max_line_length.is_some_and(|max| self.line.len() > max + 2) {
+			self.parser.push_line(std::mem::take(&mut self.line));
+		}
+		self.parser.set_partial(self.line.first().copied());
+	}
+
+	//finish marks the end of the input, so that the last line can be parsed.
+	pub fn finish(&mut self) {
+		if self.finished {
+			return;
+		}
+		self.finished = true;
+		//the last line doesn't need to end with '\n'
+		if !self.line.is_empty() {
+			self.parser.push_line(std::mem::take(&mut self.line));
+		}
+		self.parser.set_partial(None);
+		self.parser.finish();
+	}
+
+	//is_finished returns whether `finish` was called and all components were returned.
+	pub fn is_finished(&self) -> bool {
+		self.parser.is_finished()
+	}
+
+	//next_component returns the next component if all of its lines were fed already. None is returned if more
+	// input is needed (or the end of the input was reached, see `is_finished`).
+	pub fn next_component(&mut self) -> Result<Option<Component>, Error> {
+		self.parser.next_component()
+	}
+
+	//next_event returns the event of the next line if it was fed completely. It can be mixed with
+	// next_component: a component which was begun with next_event is completed by next_component, and
+	// contains the properties and sub-components which were returned as events.
+	pub fn next_event(&mut self) -> Result<Option<Event>, Error> {
+		self.parser.next_event()
+	}
+}
+
+impl Default for PushParser {
+	fn default() -> Self {
+		Self::new()
+	}
+}
@@ -0,0 +1,88 @@
+use crate::Component;
+use crate::parser::{Diagnostic, Error, Event, ParserCore, ParserOptions};
+use crate::parser::event::TreeBuilder;
+use crate::parser::reader::QueueLines;
+
+//QueueParser parses components from lines which are pushed into it. It is the base for all parsers which
+// can't block while waiting for their input.
+pub(crate) struct QueueParser {
+	core: ParserCore<'static, QueueLines>,
+	builder: TreeBuilder<Component>,
+}
+
+impl QueueParser {
+	pub fn new(options: ParserOptions) -> Self {
+		QueueParser {
+			core: ParserCore::new(QueueLines::new(options.limits.max_line_length), options),
+			builder: TreeBuilder::new(),
+		}
+	}
+
+	//push_line adds the next physical line, without the '\n' character.
+	pub fn push_line(&mut self, line: Vec<u8>) {
+		self.core.reader.source_mut().push(line);
+	}
+
+	//set_partial sets the first byte of the physical line which is currently being received, see QueueLines.
+	pub fn set_partial(&mut self, first: Option<u8>) {
+		self.core.reader.source_mut().set_partial(first);
+	}
+
+	//finish marks the end of the input, which allows parsing the last line.
+	pub fn finish(&mut self) {
+		self.core.reader.source_mut().finish();
+	}
+
+	pub fn is_finished(&self) -> bool {
+		self.core.is_finished()
+	}
+
+	pub fn options(&self) -> &ParserOptions {
+		&self.core.options
+	}
+
+	pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+		self.core.take_diagnostics()
+	}
+
+	pub fn depth(&self) -> usize {
+		self.core.open.len()
+	}
+
+	//next_event returns the event of the next line if the line is complete. The event is added to the
+	// components as well, so that next_component continues with the component the event belongs to.
+	pub fn next_event(&mut self) -> Result<Option<Event>, Error> {
+		match self.core.next_event() {
+			Ok(Some(event)) => {
+				let event = event.into_owned();
+				//a completed top-level component was returned as events already
+				self.builder.push(event.clone(), self.core.take_raw());
+				Ok(Some(event))
+			}
+			Ok(None) => Ok(None),
+			Err(e) => {
+				//the parser may have abandoned open components to recover from the error
+				self.builder.truncate(self.core.open.len());
+				Err(e)
+			}
+		}
+	}
+
+	//next_component returns the next component if all of its lines were pushed already.
+	pub fn next_component(&mut self) -> Result<Option<Component>, Error> {
+		loop {
+			match self.core.next_event() {
+				Ok(Some(event)) => if let Some(c) = self.builder.push(event.into_owned(), self.core.take_raw()) {
+					return Ok(Some(c));
+				},
+				Ok(None) => return Ok(None),
+				Err(e) => {
+					//the next component starts from scratch
+					self.core.reset();
+					self.builder.clear();
+					return Err(e);
+				}
+			}
+		}
+	}
+}
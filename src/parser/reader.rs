@@ -0,0 +1,459 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io;
+use std::io::{BufRead, Read};
+
+use crate::parser::errors::Error;
+use crate::parser::options::{Dialect, Limit, ParserOptions};
+use crate::parser::vcard21;
+use crate::Span;
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+//LineSource is a source of physical lines, split at (and without) the '\n' character.
+pub(crate) trait LineSource<'a> {
+	fn next_line(&mut self) -> Option<io::Result<Cow<'a, [u8]>>>;
+
+	fn peek_line(&mut self) -> Option<Result<&[u8], &io::Error>>;
+
+	//complete_line_available returns whether a whole unfolded line can be read without running out of lines.
+	// Sources which read from blocking inputs can always return true. With soft_breaks, a line ending with
+	// '=' may be continued by the next line (see Dialect::VCard21).
+	fn complete_line_available(&self, _lenient_line_endings: bool, _soft_breaks: bool) -> bool {
+		true
+	}
+
+	//is_finished returns false if more lines may be added to the source later, which means that running out
+	// of lines does not mean that the end of the input is reached.
+	fn is_finished(&self) -> bool {
+		true
+	}
+}
+
+//ReadLines reads lines from a BufRead, every line has to be copied. If the line length is limited, at most
+// max_line_length + 2 bytes (for CRLF) are read per line, longer lines are cut off there.
+pub(crate) struct ReadLines<R: BufRead> {
+	input: R,
+	peeked: Option<Option<io::Result<Vec<u8>>>>,
+	max_line_length: Option<usize>,
+}
+
+impl<R> ReadLines<R> where R: BufRead {
+	pub fn new(input: R, max_line_length: Option<usize>) -> Self {
+		ReadLines { input, peeked: None, max_line_length }
+	}
+
+	//read reads the next line like BufRead::split does.
+	fn read(&mut self) -> Option<io::Result<Vec<u8>>> {
+		let mut buf = Vec::new();
+		let res = match self.max_line_length {
+			Some(max) => (&mut self.input).take(max as u64 + 2).read_until(b'\n', &mut buf),
+			None => self.input.read_until(b'\n', &mut buf),
+		};
+		match res {
+			Ok(0) => None,
+			Ok(_) => {
+				if buf.last() == Some(&b'\n') {
+					buf.pop();
+				}
+				Some(Ok(buf))
+			}
+			Err(e) => Some(Err(e)),
+		}
+	}
+}
+
+impl<R> LineSource<'static> for ReadLines<R> where R: BufRead {
+	fn next_line(&mut self) -> Option<io::Result<Cow<'static, [u8]>>> {
+		let line = match self.peeked.take() {
+			Some(line) => line,
+			None => self.read(),
+		};
+		line.map(|res| res.map(Cow::Owned))
+	}
+
+	fn peek_line(&mut self) -> Option<Result<&[u8], &io::Error>> {
+		if self.peeked.is_none() {
+			self.peeked = Some(self.read());
+		}
+		self.peeked.as_ref().unwrap().as_ref().map(|res| res.as_deref())
+	}
+}
+
+//SliceLines splits lines off a byte slice without copying them.
+pub(crate) struct SliceLines<'a>(&'a [u8]);
+
+impl<'a> SliceLines<'a> {
+	pub fn new(input: &'a [u8]) -> Self {
+		SliceLines(input)
+	}
+
+	//split returns the next line and the remaining input, behaving like BufRead::split:
+	// an empty input has no lines and the last line may end without '\n'.
+	fn split(input: &'a [u8]) -> Option<(&'a [u8], &'a [u8])> {
+		if input.is_empty() {
+			return None;
+		}
+		Some(match input.iter().position(|&b| b == b'\n') {
+			Some(i) => (&input[..i], &input[i + 1..]),
+			None => (input, &input[input.len()..]),
+		})
+	}
+}
+
+impl<'a> LineSource<'a> for SliceLines<'a> {
+	fn next_line(&mut self) -> Option<io::Result<Cow<'a, [u8]>>> {
+		let (line, rest) = Self::split(self.0)?;
+		self.0 = rest;
+		Some(Ok(Cow::Borrowed(line)))
+	}
+
+	fn peek_line(&mut self) -> Option<Result<&[u8], &io::Error>> {
+		Self::split(self.0).map(|(line, _)| Ok(line))
+	}
+}
+
+//QueueLines holds lines which were pushed into it, until they are read.
+#[derive(Default)]
+pub(crate) struct QueueLines {
+	lines: VecDeque<Vec<u8>>,
+	finished: bool,
+	//the number of bytes in lines
+	buffered: usize,
+	//if more bytes are buffered, the first line is considered to be complete, so that it can be read and
+	// rejected for being too long instead of waiting for its end.
+	max_buffered: Option<usize>,
+	//the first byte of the line which is currently being received, if it is known
+	partial: Option<u8>,
+}
+
+impl QueueLines {
+	pub fn new(max_line_length: Option<usize>) -> Self {
+		QueueLines {
+			max_buffered: max_line_length.map(|max| max + 2),
+			..Self::default()
+		}
+	}
+
+	//push adds a line, without the '\n' character.
+	pub fn push(&mut self, line: Vec<u8>) {
+		self.buffered += line.len();
+		self.lines.push_back(line);
+	}
+
+	//set_partial sets the first byte of the line which is currently being received, so that the previous line
+	// is known to be complete if the byte does not begin a continuation line.
+	pub fn set_partial(&mut self, first: Option<u8>) {
+		self.partial = first;
+	}
+
+	//finish marks the end of the input, no lines may be pushed afterwards.
+	pub fn finish(&mut self) {
+		self.finished = true;
+	}
+}
+
+impl LineSource<'static> for QueueLines {
+	fn next_line(&mut self) -> Option<io::Result<Cow<'static, [u8]>>> {
+		let line = self.lines.pop_front()?;
+		self.buffered -= line.len();
+		Some(Ok(Cow::Owned(line)))
+	}
+
+	fn peek_line(&mut self) -> Option<Result<&[u8], &io::Error>> {
+		self.lines.front().map(|line| Ok(line.as_slice()))
+	}
+
+	fn complete_line_available(&self, lenient_line_endings: bool, soft_breaks: bool) -> bool {
+		if self.finished || self.max_buffered.is_some_and(|max| self.buffered > max) {
+			return true;
+		}
+		//the line is complete if another line follows which is not a continuation of it. In lenient mode,
+		// empty lines are skipped.
+		let blank = |line: &Vec<u8>| lenient_line_endings && (line.is_empty() || line.as_slice() == b"\r");
+		let soft_break = |line: &Vec<u8>| soft_breaks && line.strip_suffix(b"\r").unwrap_or(line).ends_with(b"=");
+		let start = match self.lines.iter().position(|line| !blank(line)) {
+			Some(start) => start,
+			None => return false,
+		};
+		let mut prev = &self.lines[start];
+		for line in self.lines.iter().skip(start + 1) {
+			if !is_continuation(line) && !soft_break(prev) {
+				return true;
+			}
+			prev = line;
+		}
+		self.partial.is_some_and(|b| b != b' ' && b != b'\t' && !soft_break(prev))
+	}
+
+	fn is_finished(&self) -> bool {
+		self.finished && self.lines.is_empty()
+	}
+}
+
+//LineReader reads unfolded lines from a LineSource and keeps track of line numbers and byte offsets.
+pub(crate) struct LineReader<S> {
+	source: S,
+	//the number of the first physical line of the last unfolded line
+	pub line: u32,
+	//the number of the next physical line
+	pub next_line: u32,
+	//the byte offset of the next physical line
+	next_offset: usize,
+	//the physical lines the last unfolded line consists of
+	segments: Vec<Segment>,
+	lenient_line_endings: bool,
+	//join lines ending with a quoted-printable soft line break, see Dialect::VCard21
+	soft_breaks: bool,
+	max_line_length: Option<usize>,
+	max_total_bytes: Option<usize>,
+	//the end of the last physical line which was read (without its line ending), for end_span
+	last_end: Span,
+	//the physical lines of the last unfolded line, if they are kept (see ParserOptions::lossless)
+	raw: Option<Vec<u8>>,
+	//the line ending problems which were tolerated, if diagnostics are collected.
+	pub problems: Option<Vec<(Error, Span)>>,
+}
+
+//Segment is the part of an unfolded line which was read from a single physical line.
+struct Segment {
+	//position in the unfolded line
+	pos: usize,
+	//byte offset of the segment in the input
+	offset: usize,
+	//byte offset of the physical line in the input, this differs from offset for continuation lines.
+	line_offset: usize,
+	line: u32,
+}
+
+impl<'a, S> LineReader<S> where S: LineSource<'a> {
+	pub fn new(source: S, options: &ParserOptions) -> Self {
+		LineReader {
+			source,
+			line: 0,
+			next_line: 1,
+			next_offset: 0,
+			segments: Vec::new(),
+			lenient_line_endings: options.lenient_line_endings,
+			soft_breaks: options.dialect == Dialect::VCard21,
+			max_line_length: options.limits.max_line_length,
+			max_total_bytes: options.limits.max_total_bytes,
+			last_end: Span::default(),
+			raw: if options.lossless { Some(Vec::new()) } else { None },
+			problems: if options.collect_diagnostics { Some(Vec::new()) } else { None },
+		}
+	}
+
+	//start_at sets the number and byte offset of the first line, for sources which don't start at the
+	// beginning of the input.
+	#[cfg(feature = "rayon")]
+	pub fn start_at(&mut self, line: u32, offset: usize) {
+		self.next_line = line;
+		self.next_offset = offset;
+	}
+
+	pub fn peek_line(&mut self) -> Option<Result<&[u8], &io::Error>> {
+		self.source.peek_line()
+	}
+
+	pub fn is_finished(&self) -> bool {
+		self.source.is_finished()
+	}
+
+	//next_offset returns the byte offset of the next physical line.
+	pub fn next_offset(&self) -> usize {
+		self.next_offset
+	}
+
+	pub fn source_mut(&mut self) -> &mut S {
+		&mut self.source
+	}
+
+	//take_raw returns the physical lines of the last unfolded line (including skipped empty lines before it)
+	// if they are kept. They are returned only once.
+	pub fn take_raw(&mut self) -> Option<Vec<u8>> {
+		self.raw.as_mut().map(std::mem::take)
+	}
+
+	//read_unfolded_line reads the next logical line. The returned line only borrows from the source if it
+	// was not folded. If the source has not yet received the whole line, None is returned as well.
+	pub fn read_unfolded_line(&mut self) -> Result<Option<Cow<'a, [u8]>>, Error> {
+		self.line = self.next_line;
+		if !self.source.complete_line_available(self.lenient_line_endings || self.problems.is_some(), self.soft_breaks) {
+			return Ok(None);
+		}
+		self.segments.clear();
+		if let Some(raw) = &mut self.raw {
+			raw.clear();
+		}
+
+		let mut buf;
+		loop {
+			let mut offset = self.next_offset;
+			match self.read_physical_line()? {
+				None => return Ok(None), //Reached EOF
+				Some(line) => buf = line,
+			}
+			//a UTF-8 byte order mark at the beginning of the input is skipped
+			if offset == 0 && buf.starts_with(BOM) {
+				strip_first(&mut buf, BOM.len());
+				offset = BOM.len();
+			}
+			//in lenient mode, empty lines are skipped and the next line will be the start of the unfolded line.
+			// The same applies to empty lines without CR if their problem was tolerated.
+			if buf.is_empty() && (self.lenient_line_endings || self.tolerated(self.next_line - 1)) {
+				self.line = self.next_line;
+				continue;
+			}
+			self.segments.push(Segment { pos: 0, offset, line_offset: offset, line: self.line });
+			break;
+		}
+
+		// peek at next line. If next line begins with a space or HTAB (\t), 'unfold' it.
+		// if it would throw an error, don't return it (it's only borrowed), force another read which will also trigger it.
+		// if there is nothing to read or the next line begins with another character, don't unfold.
+		loop {
+			//a quoted-printable soft line break continues the value with the whole next line, even if it
+			// begins with a space.
+			let soft_break = self.soft_breaks && vcard21::has_soft_break(&buf);
+			match self.source.peek_line() {
+				Some(Err(_)) => {}
+				Some(Ok(next_line)) if soft_break && !next_line.is_empty() => {}
+				Some(Ok(next_line)) if is_continuation(next_line) => {}
+				_ => break,
+			}
+			let offset = self.next_offset;
+			let next = self.read_physical_line()?.unwrap();
+			//remove the '=' of a soft line break or the space/htab at the front of a folded line and append
+			let skip = if soft_break { 0 } else { 1 };
+			if soft_break {
+				strip_last(&mut buf);
+			}
+			self.segments.push(Segment { pos: buf.len(), offset: offset + skip, line_offset: offset, line: self.next_line - 1 });
+			if let Some(max) = self.max_line_length {
+				if buf.len() + next.len() - skip > max {
+					return Err(Error::limit_error(Limit::LineLength, max, self.line));
+				}
+			}
+			buf.to_mut().extend_from_slice(&next[skip..]);
+		}
+		Ok(Some(buf))
+	}
+
+	//read_physical_line reads the next line from the source and removes its line ending.
+	fn read_physical_line(&mut self) -> Result<Option<Cow<'a, [u8]>>, Error> {
+		let mut buf = match self.source.next_line() {
+			None => return Ok(None), //Reached EOF
+			Some(line) => line?,
+		};
+		if let Some(raw) = &mut self.raw {
+			raw.extend_from_slice(&buf);
+			//only the last line of the input may lack the '\n'
+			if buf.last() == Some(&b'\r') || !self.source.is_finished() || self.source.peek_line().is_some() {
+				raw.push(b'\n');
+			}
+		}
+		// increment line counter and offset (including the '\n')
+		let len = buf.strip_suffix(b"\r").unwrap_or(&buf).len();
+		self.last_end = Span { start: self.next_offset + len, end: self.next_offset + len, start_line: self.next_line, end_line: self.next_line, column: len + 1 };
+		self.next_line += 1;
+		self.next_offset += buf.len() + 1;
+		if let Some(max) = self.max_total_bytes {
+			//the '\n' of the last line may be missing, so it is not counted
+			if self.next_offset - 1 > max {
+				return Err(Error::limit_error(Limit::TotalBytes, max, self.next_line - 1));
+			}
+		}
+		if let Some(max) = self.max_line_length {
+			let len = if buf.last() == Some(&b'\r') { buf.len() - 1 } else { buf.len() };
+			if len > max {
+				return Err(Error::limit_error(Limit::LineLength, max, self.next_line - 1));
+			}
+		}
+		// all lines have to end with a \r\n. Empty lines without a \r\n are also not allowed. empty lines at the end return EOF (represented as Ok(None)
+		if buf.last() == Some(&b'\r') {
+			strip_last(&mut buf);
+		} else if buf.is_empty() && self.source.peek_line().is_none() {
+			//this is the last line (after \r\n) and it is empty.
+			return Ok(None);
+		} else if !self.lenient_line_endings {
+			//either this line doesn't end with \r\n or there are some lines following and this line contains only "\n" => is not allowed!
+			let has_next = self.source.peek_line().is_some();
+			let e = Error::crlf_error(buf.to_vec(), self.line, has_next);
+			match &mut self.problems {
+				//point at the end of the line, where the CR is missing
+				Some(problems) => {
+					let end = self.next_offset - 1;
+					let line = self.next_line - 1;
+					problems.push((e, Span { start: end, end, start_line: line, end_line: line, column: buf.len() + 1 }));
+				}
+				None => return Err(e),
+			}
+		}
+		Ok(Some(buf))
+	}
+
+	//tolerated returns whether a line ending problem in the given physical line was tolerated.
+	fn tolerated(&self, line: u32) -> bool {
+		self.problems.as_ref().and_then(|p| p.last()).is_some_and(|(_, span)| span.start_line == line)
+	}
+
+	//end_span returns an empty span at the end of the last line which was read so far.
+	pub fn end_span(&self) -> Span {
+		self.last_end
+	}
+
+	//span maps the positions start..end of the last unfolded line back to the input.
+	pub fn span(&self, start: usize, end: usize) -> Span {
+		//the segment containing start, and the one containing the last byte before end.
+		let first = self.segment(start, false);
+		let last = if end > start { self.segment(end, true) } else { first };
+		Span {
+			start: first.offset + start - first.pos,
+			end: last.offset + end - last.pos,
+			start_line: first.line,
+			end_line: last.line,
+			column: first.offset + start - first.pos - first.line_offset + 1,
+		}
+	}
+
+	//fragments splits the positions start..end of the last unfolded line at the folds and returns the byte
+	// ranges of the parts in the input, one for every physical line.
+	pub fn fragments(&self, start: usize, end: usize) -> Vec<(usize, usize)> {
+		let mut out = Vec::new();
+		for (i, s) in self.segments.iter().enumerate() {
+			let next = self.segments.get(i + 1).map_or(usize::MAX, |n| n.pos);
+			let (from, to) = (start.max(s.pos), end.min(next));
+			if from < to {
+				out.push((s.offset + from - s.pos, s.offset + to - s.pos));
+			}
+		}
+		out
+	}
+
+	fn segment(&self, pos: usize, is_end: bool) -> &Segment {
+		self.segments.iter()
+				.rev()
+				.find(|s| s.pos < pos || (s.pos == pos && !is_end) || s.pos == 0)
+				.expect("LineReader::segment: no line was read")
+	}
+}
+
+//is_continuation returns whether the line begins with a space or HTAB, which means that it continues the previous line.
+fn is_continuation(line: &[u8]) -> bool {
+	line.first().is_some_and(|&b| b == b' ' || b == b'\t')
+}
+
+fn strip_first(buf: &mut Cow<[u8]>, n: usize) {
+	match buf {
+		Cow::Borrowed(b) => *b = &b[n..],
+		Cow::Owned(b) => { b.drain(..n); }
+	}
+}
+
+fn strip_last(buf: &mut Cow<[u8]>) {
+	match buf {
+		Cow::Borrowed(b) => *b = &b[..b.len() - 1],
+		Cow::Owned(b) => { b.pop(); }
+	}
+}
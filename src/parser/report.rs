@@ -0,0 +1,252 @@
+use core::fmt;
+
+use crate::parser::{Error, ErrorKind};
+use crate::parser::options::{Dialect, Limits, ParserOptions};
+use crate::parser::reader::{LineReader, SliceLines};
+
+//Report renders an error in the style of compiler diagnostics: the physical lines the error was found in
+// (including the continuation lines of a folded line) with their line numbers, a caret underline below the
+// offending text, the component the error occurred in and a help note for common mistakes. The input has
+// to be the same the error was returned for, and it is unfolded like the parser did (see new_with_options).
+pub struct Report<'a> {
+	error: &'a Error,
+	input: &'a [u8],
+	dialect: Dialect,
+}
+
+impl<'a> Report<'a> {
+	pub fn new(error: &'a Error, input: &'a [u8]) -> Self {
+		Self::new_with_options(error, input, ParserOptions::default())
+	}
+
+	//new_with_options creates a Report for an error returned by a parser with these options. Only the dialect
+	// is used, which decides where folded lines continue.
+	pub fn new_with_options(error: &'a Error, input: &'a [u8], options: ParserOptions) -> Self {
+		Report { error, input, dialect: options.dialect }
+	}
+}
+
+impl Error {
+	//report returns a Report of this error for the input it was returned for.
+	pub fn report<'a>(&'a self, input: &'a [u8]) -> Report<'a> {
+		Report::new(self, input)
+	}
+
+	//report_with_options returns a Report of this error for the input and the options it was returned for.
+	pub fn report_with_options<'a>(&'a self, input: &'a [u8], options: ParserOptions) -> Report<'a> {
+		Report::new_with_options(self, input, options)
+	}
+}
+
+impl fmt::Display for Report<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+		let error = match self.error {
+			Error::Recovered { error, .. } => error.as_ref(),
+			e => e,
+		};
+		writeln!(f, "error[{}]: {}", error.code(), message(error))?;
+		let line = match error.line() {
+			Some(line) => line,
+			None => return Ok(()),
+		};
+		let lines = UnfoldedLine::read(self.input, line, self.dialect);
+		let physical = lines.as_ref().map_or(&[][..], |l| &l.lines[..]);
+		let width = (line as usize + physical.len().max(1) - 1).to_string().len();
+		match error.column() {
+			Some(column) => writeln!(f, "{:w$}--> line {}, column {}", "", line, column, w = width)?,
+			None => writeln!(f, "{:w$}--> line {}", "", line, w = width)?,
+		}
+		if !physical.is_empty() {
+			writeln!(f, "{:w$} |", "", w = width)?;
+		}
+		//the offending text in the input, split at the folds
+		let carets = match (&lines, error.column()) {
+			(Some(lines), Some(column)) => lines.carets(column - 1, error.offending_text().map_or(0, str::len)),
+			_ => Vec::new(),
+		};
+		for (i, (_, l)) in physical.iter().enumerate() {
+			writeln!(f, "{:>w$} | {}", line as usize + i, l, w = width)?;
+			if let Some(&(_, indent, n)) = carets.iter().find(|c| c.0 == i) {
+				let label = if carets.last().is_some_and(|c| c.0 == i) { label(error) } else { String::new() };
+				writeln!(f, "{:w$} | {:i$}{} {}", "", "", "^".repeat(n), label, w = width, i = indent)?;
+			}
+		}
+		if let Some(last) = error.path().and_then(|p| p.last()) {
+			writeln!(f, "{:w$} = note: in component {} (BEGIN in line {})", "", error.path().unwrap(), last.line, w = width)?;
+		}
+		if let Error::Recovered { first_line, last_line, .. } = self.error {
+			writeln!(f, "{:w$} = note: lines {}-{} were skipped to recover from the error", "", first_line, last_line, w = width)?;
+		}
+		if let Some(help) = help(error) {
+			writeln!(f, "{:w$} = help: {}", "", help, w = width)?;
+		}
+		Ok(())
+	}
+}
+
+//message returns what went wrong, without the context of the line.
+fn message(e: &Error) -> String {
+	match e {
+		Error::Malformed { message, .. } => message.clone(),
+		Error::UnexpectedEOF { expected, .. } => format!("{}, expected END:{}", e.kind(), expected),
+		Error::LimitExceeded { limit, max, .. } => format!("{}: the {} must not be larger than {}", e.kind(), limit, max),
+		Error::Io(e) => e.to_string(),
+		Error::Utf8 { error, .. } => format!("{}: {}", ErrorKind::InvalidUtf8, error),
+		e => e.kind().to_string(),
+	}
+}
+
+//label returns the text next to the carets.
+fn label(e: &Error) -> String {
+	match e.expected().as_slice() {
+		[] => String::new(),
+		[token] => format!("expected {:?}", token),
+		tokens => format!("expected one of {}", tokens.iter().map(|t| format!("{:?}", t)).collect::<Vec<_>>().join(", ")),
+	}
+}
+
+//component returns the name of the innermost component the error occurred in, if it is known.
+fn component(e: &Error) -> Option<String> {
+	e.path().and_then(|p| p.last()).map(|last| last.name.to_uppercase())
+}
+
+fn help(e: &Error) -> Option<String> {
+	Some(match e.kind() {
+		ErrorKind::UnexpectedEof | ErrorKind::MismatchedEnd => match component(e) {
+			Some(name) => format!("add \"END:{}\" to end the component", name),
+			None => return None,
+		},
+		ErrorKind::ExpectedBegin => "properties must be inside of a component, which starts with \"BEGIN:<name>\"".to_string(),
+		ErrorKind::MissingCarriageReturn => "lines have to end with CRLF, ParserOptions::lenient() also accepts LF".to_string(),
+		ErrorKind::ExpectedEquals | ErrorKind::EmptyParameterName => "parameters are written as \";NAME=value\" before the ':'".to_string(),
+		ErrorKind::UnterminatedQuotedValue => "add the closing '\"' of the parameter value".to_string(),
+		ErrorKind::InvalidName | ErrorKind::InvalidGroupedName | ErrorKind::InvalidComponentName =>
+			"names may only contain ASCII letters, digits and '-'".to_string(),
+		ErrorKind::LimitExceeded => "the limits can be changed in ParserOptions::limits".to_string(),
+		ErrorKind::InvalidUtf8 => "the input has to be encoded in UTF-8".to_string(),
+		ErrorKind::UnsupportedCharset => "supported character sets are UTF-8, US-ASCII, ISO-8859-1, ISO-8859-15 and WINDOWS-1252".to_string(),
+		_ => return None,
+	})
+}
+
+//UnfoldedLine is an unfolded line of the input, read like the parser does.
+struct UnfoldedLine<'a> {
+	input: &'a [u8],
+	reader: LineReader<SliceLines<'a>>,
+	//the byte offsets of the physical lines the unfolded line consists of and the lines without their line
+	// endings, with invalid UTF-8 replaced.
+	lines: Vec<(usize, String)>,
+}
+
+impl<'a> UnfoldedLine<'a> {
+	//read reads the unfolded line which starts in the given line, if there is one.
+	fn read(input: &'a [u8], line: u32, dialect: Dialect) -> Option<Self> {
+		//errors don't matter here, the line has been read by the parser before.
+		let options = ParserOptions { lenient_line_endings: true, limits: Limits::default(), dialect, ..ParserOptions::default() };
+		let mut reader = LineReader::new(SliceLines::new(input), &options);
+		while reader.line < line {
+			reader.read_unfolded_line().ok()??;
+		}
+		if reader.line != line {
+			return None;
+		}
+		let mut offset = input.split(|&b| b == b'\n').take(line as usize - 1).map(|l| l.len() + 1).sum();
+		let lines = input[offset..].split(|&b| b == b'\n').take((reader.next_line - line) as usize).map(|l| {
+			let start = offset;
+			offset += l.len() + 1;
+			(start, String::from_utf8_lossy(l.strip_suffix(b"\r").unwrap_or(l)).into_owned())
+		}).collect();
+		Some(UnfoldedLine { input, reader, lines })
+	}
+
+	//carets maps the len bytes at start of the unfolded line to the physical lines and returns the index of
+	// the line, the number of characters before the carets and the number of carets for each part.
+	fn carets(&self, start: usize, len: usize) -> Vec<(usize, usize, usize)> {
+		let fragments = if len == 0 {
+			let span = self.reader.span(start, start);
+			vec![(span.start, span.start)]
+		} else {
+			self.reader.fragments(start, start + len)
+		};
+		fragments.into_iter().filter_map(|(from, to)| {
+			let i = self.lines.iter().rposition(|&(offset, _)| offset <= from)?;
+			let chars = |b: &[u8]| String::from_utf8_lossy(b).chars().count();
+			Some((i, chars(&self.input[self.lines[i].0..from]), chars(&self.input[from..to]).max(1)))
+		}).collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{BorrowedParser, Dialect, ParserOptions};
+
+	fn report(input: &str) -> String {
+		let e = BorrowedParser::new(input).next_component().unwrap_err();
+		e.report(input.as_bytes()).to_string()
+	}
+
+	#[test]
+	fn malformed() {
+		assert_eq!(report("BEGIN:co\r\nwas;x\"y:z\r\n"), "\
+error[expected-equals]: expected '='
+ --> line 2, column 6
+  |
+2 | was;x\"y:z
+  |      ^ expected \"=\"
+  = note: in component co[0] (BEGIN in line 1)
+  = help: parameters are written as \";NAME=value\" before the ':'
+");
+	}
+
+	#[test]
+	fn folded() {
+		assert_eq!(report("BEGIN:co\r\nwas;x=\r\n 1:a\r\nend:\r\n b\r\n"), "\
+error[mismatched-end]: expected \"END:CO\"
+ --> line 4, column 5
+  |
+4 | end:
+5 |  b
+  |  ^ expected \"END:CO\"
+  = note: in component co[0] (BEGIN in line 1)
+  = help: add \"END:CO\" to end the component
+");
+	}
+
+	#[test]
+	fn soft_break() {
+		let input: &[u8] = b"BEGIN:VCARD\r\nNOTE;ENCODING=QUOTED-PRINTABLE:ab=\r\ncd\xffe\r\nEND:VCARD\r\n";
+		let options = ParserOptions { dialect: Dialect::VCard21, ..ParserOptions::default() };
+		let e = BorrowedParser::new_with_options(input, options.clone()).next_component().unwrap_err();
+		assert_eq!(e.report_with_options(input, options).to_string(), "\
+error[invalid-utf8]: invalid UTF-8: invalid utf-8 sequence of 1 bytes from index 35
+ --> line 2, column 36
+  |
+2 | NOTE;ENCODING=QUOTED-PRINTABLE:ab=
+3 | cd\u{fffd}e
+  |   ^ \n  = note: in component VCARD[0] (BEGIN in line 1)
+  = help: the input has to be encoded in UTF-8
+");
+	}
+
+	#[test]
+	fn crlf() {
+		assert_eq!(report("BEGIN:co\n"), "\
+error[missing-carriage-return]: expected CR ('\\r') before LF
+ --> line 1, column 9
+  |
+1 | BEGIN:co
+  |         ^ expected \"\\r\"
+  = help: lines have to end with CRLF, ParserOptions::lenient() also accepts LF
+");
+	}
+
+	#[test]
+	fn unexpected_eof() {
+		assert_eq!(report("BEGIN:co\r\n"), "\
+error[unexpected-eof]: unexpected end of file or stream, expected END:CO
+ --> line 2
+  = note: in component co[0] (BEGIN in line 1)
+  = help: add \"END:CO\" to end the component
+");
+	}
+}
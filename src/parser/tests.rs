@@ -1,14 +1,19 @@
-use std::io::Cursor;
-use crate::Parser;
+use std::borrow::Cow;
+use std::io::{Cursor, Read};
+use crate::{BorrowedEvent, BorrowedParser, Charset, Decoding, Diagnostic, EncoderOptions, ErrorKind, Event, EventReader, Limits, Parser, ParserOptions, PushParser, Severity, Span};
 use crate::test_helper::c2;
 use crate::test_helper::c;
 use crate::test_helper::p2;
 use crate::test_helper::p;
+use crate::test_helper::pg;
 use crate::test_helper::pm;
 use crate::test_helper::test_parse;
 use crate::test_helper::assert_comp_equal;
 use crate::test_helper::test_parse_bytes;
 use crate::test_helper::test_parse_error;
+use crate::test_helper::expect_err;
+use crate::test_helper::test_parse_error_with_options;
+use crate::test_helper::test_parse_with_options;
 
 //TESTS: Successful parses
 
@@ -76,6 +81,50 @@ fn parse_complex(){
 		 ]))],vec![]))
 }
 
+#[test]
+fn parse_repeated_parameter(){
+	let input="BEGIN:vcard\r\nTEL;TYPE=home,voice;PREF=1;type=cell:123\r\nEND:vcard\r\n";
+	test_parse(input,
+		 c("VCARD",vec![p("TEL", "123", pm(vec![
+			 ("TYPE",vec!["home", "voice"]),
+			 ("PREF",vec!["1"]),
+			 ("TYPE",vec!["cell"])
+		 ]))],vec![]));
+	let comp=BorrowedParser::new(input).next_component().unwrap().unwrap().into_owned();
+	let prop=&comp.properties[0];
+	assert_eq!(prop.get_param_value("TYPE").unwrap(), &vec!["home", "voice"]);
+	assert_eq!(prop.get_param_values("TYPE"), vec!["home", "voice", "cell"]);
+	//the encoder writes the parameters back in the same form
+	assert_eq!(comp.encode_to_string(), "BEGIN:VCARD\r\nTEL;TYPE=home,voice;PREF=1;TYPE=cell:123\r\nEND:VCARD\r\n");
+}
+
+#[test]
+fn parse_groups(){
+	let input="BEGIN:VCARD\r\nitem1.EMAIL;TYPE=INTERNET:a@b.c\r\nitem1.X-ABLabel:work\r\nitem2.EMAIL:d@e.f\r\nFN:x\r\nEND:VCARD\r\n";
+	test_parse(input,
+		 c("VCARD",vec![
+			 pg("item1", "EMAIL", "a@b.c", pm(vec![("TYPE",vec!["INTERNET"])])),
+			 pg("ITEM1", "X-ABLABEL", "work", pm(vec![])),
+			 pg("item2", "EMAIL", "d@e.f", pm(vec![])),
+			 p2("FN", "x"),
+		 ],vec![]));
+	let comp=BorrowedParser::new(input).next_component().unwrap().unwrap().into_owned();
+	assert_eq!(comp.groups(), vec!["item1", "item2"]);
+	assert_eq!(comp.find_group("ITEM1").len(), 2);
+	assert_eq!(comp.find_property_in_group("item1", "email")[0].value, "a@b.c");
+	assert_eq!(comp.find_property_in_group("item2", "email")[0].value, "d@e.f");
+	assert!(comp.find_property_in_group("item3", "email").is_empty());
+	//properties of all groups are found by their name
+	assert_eq!(comp.find_property("EMAIL").len(), 2);
+}
+
+#[test]
+fn parse_group_errors(){
+	test_parse_error("BEGIN:VCARD\r\nitem1.:x\r\nEND:VCARD\r\n", "expected one or more alphanumerical characters or '-' after the group");
+	test_parse_error("BEGIN:VCARD\r\nitem1.a.b:x\r\nEND:VCARD\r\n", "expected ':' or ';'");
+	test_parse_error("item1.BEGIN:VCARD\r\nEND:VCARD\r\n", "expected BEGIN");
+}
+
 #[test]
 fn parse_nested_component(){
 	test_parse("BEGIN:comp\r\nBEGIN:iNnErCoMp\r\nFEATURE;LAng=\"e;n\":LoremIpsum\r\nEND:InNeRcOmP\r\nEND:Comp\r\n",
@@ -117,7 +166,7 @@ fn parse_utf_splitting_fold(){
 	let expected=c("COMP", vec![p2("FEATURE", "\u{2764}Content:'!,;.'")],vec![]);
 
 	let prefix="BEGIN:comp\r\nFEATURE:";
-	let bytes=vec![0xE2_u8,0x9D,0xA4];//UTF8 Heart Character
+	let bytes=[0xE2_u8,0x9D,0xA4];//UTF8 Heart Character
 	let fold="\r\n ";
 	let suffix="Content:'!,;.'\r\nEND:Comp\r\n";
 
@@ -159,6 +208,213 @@ fn parse_no_component2(){
 	}
 }
 
+#[test]
+fn parse_lenient_lf(){
+	test_parse_with_options(b"BEGIN:comp\nFEATURE:Conten\n t:'!,;.'\nEND:Comp\n", ParserOptions::lenient(),
+		 c("COMP", vec![p2("FEATURE", "Content:'!,;.'")],vec![]))
+}
+
+#[test]
+fn parse_lenient_mixed(){
+	test_parse_with_options(b"BEGIN:comp\r\nFEATURE:Conten\n t:'!,;.'\r\nBEGIN:inner\nEND:inner\r\nEND:Comp\n", ParserOptions::lenient(),
+		 c("COMP", vec![p2("FEATURE", "Content:'!,;.'")],vec![c2("INNER")]))
+}
+
+#[test]
+fn parse_lenient_missing_last_terminator(){
+	test_parse_with_options(b"BEGIN:comp\r\nFEATURE:Conten\r\n t:'!,;.'\r\nEND:Comp", ParserOptions::lenient(),
+		 c("COMP", vec![p2("FEATURE", "Content:'!,;.'")],vec![]))
+}
+
+#[test]
+fn parse_lenient_empty_lines(){
+	test_parse_with_options(b"\nBEGIN:comp\n\r\nFEATURE:Content\nEND:Comp\n\n\n", ParserOptions::lenient(),
+		 c("COMP", vec![p2("FEATURE", "Content")],vec![]))
+}
+
+#[test]
+fn parse_lenient_two_components(){
+	let input="BEGIN:comp\nEND:Comp\nBEGIN:porp\nEND:poRp";
+	let x=Cursor::new(input.as_bytes());
+	let mut p=Parser::new_with_options(x, ParserOptions::lenient());
+	assert_comp_equal(&p.next_component().unwrap().unwrap(),&c2("COMP"));
+	assert_comp_equal(&p.next_component().unwrap().unwrap(),&c2("PORP"));
+	assert!(p.next_component().unwrap().is_none());
+}
+
+//TESTS: BORROWED PARSER
+
+#[allow(clippy::ptr_arg)]
+fn is_borrowed(s:&Cow<str>)->bool{
+	matches!(s, Cow::Borrowed(_))
+}
+
+#[test]
+fn borrowed_without_copies(){
+	let input="BEGIN:COMP\r\nFEATURE;LANG=en,\"e;n\":Lorem Ipsum\r\nEND:COMP\r\n";
+	let mut p=BorrowedParser::new(input);
+	let c=p.next_component().unwrap().unwrap();
+	assert!(is_borrowed(c.name.get_ref()));
+	let prop=&c.properties[0];
+	assert!(is_borrowed(prop.name.get_ref()));
+	assert!(is_borrowed(&prop.value));
+	assert_eq!(prop.value, "Lorem Ipsum");
+	let values=prop.get_param_value("LANG").unwrap();
+	assert_eq!(values, &vec!["en","e;n"]);
+	assert!(values.iter().all(is_borrowed));
+	assert!(p.next_component().unwrap().is_none());
+}
+
+#[test]
+fn borrowed_copies_changed_text(){
+	let input="BEGIN:comp\r\nFEATURE;LANG=e^^^n:Conten\r\n t\r\nEND:Comp\r\n";
+	let c=BorrowedParser::new(input.as_bytes()).next_component().unwrap().unwrap();
+	//names keep their original spelling, so they are never copied
+	assert!(is_borrowed(c.name.get_ref()));
+	assert_eq!(c.name, "COMP");
+	assert_eq!(c.name.as_str(), "comp");
+	let prop=&c.find_property("FEATURE")[0];
+	assert!(!is_borrowed(&prop.value));
+	assert_eq!(prop.value, "Content");
+	assert!(!is_borrowed(&prop.get_param_value("LANG").unwrap()[0]));
+}
+
+#[test]
+fn borrowed_iterator(){
+	let input="BEGIN:comp\r\nEND:Comp\r\nBEGIN:porp\r\nEND:poRp\r\n".to_string();
+	let got:Vec<_>=BorrowedParser::from(input.as_str()).map(|c|c.unwrap().into_owned()).collect();
+	assert_eq!(got.len(), 2);
+	assert_comp_equal(&got[0],&c2("COMP"));
+	assert_comp_equal(&got[1],&c2("PORP"));
+}
+
+//TESTS: EVENTS
+
+fn event_string(e:&Event)->String{
+	match e{
+		Event::BeginComponent(name, _) => format!("BEGIN {}",name),
+		Event::Property(p) => format!("{}={}",p.name,p.value),
+		Event::EndComponent(name, _) => format!("END {}",name),
+	}
+}
+
+#[test]
+fn events_nested(){
+	let input="BEGIN:cal\r\nVERSION:2.0\r\nBEGIN:event\r\nsummary:a\r\nEND:event\r\nBEGIN:event\r\nEND:event\r\nEND:cal\r\nBEGIN:cal\r\nEND:cal\r\n";
+	let mut r=EventReader::new(Cursor::new(input));
+	let mut got=vec![];
+	while let Some(e)=r.next_event().unwrap(){
+		got.push(format!("{}:{}",r.depth(),event_string(&e)));
+	}
+	assert_eq!(got, vec!["1:BEGIN cal", "1:VERSION=2.0", "2:BEGIN event", "2:summary=a", "1:END event",
+		"2:BEGIN event", "1:END event", "0:END cal", "1:BEGIN cal", "0:END cal"]);
+}
+
+#[test]
+fn events_continue_after_error(){
+	let input="BEGIN:cal\r\nwas;x\r\nsummary:a\r\nEND:cal\r\n";
+	let got:Vec<_>=EventReader::new(Cursor::new(input)).map(|e|e.map(|e|event_string(&e))).collect();
+	assert_eq!(got.len(), 4);
+	assert!(got[1].is_err());
+	assert_eq!(got[2].as_ref().unwrap(), "summary=a");
+	assert_eq!(got[3].as_ref().unwrap(), "END cal");
+}
+
+#[test]
+fn events_unexpected_eof(){
+	let input="BEGIN:cal\r\nBEGIN:event\r\n";
+	let mut r=EventReader::new(Cursor::new(input));
+	r.next_event().unwrap();
+	r.next_event().unwrap();
+	expect_err(r.next_event(), "line 3: \tunexpected end of file or stream, expected END:EVENT");
+}
+
+#[test]
+fn events_borrowed(){
+	let mut p=BorrowedParser::new("BEGIN:CAL\r\nVERSION:2.0\r\nEND:CAL\r\n");
+	match p.next_event().unwrap().unwrap(){
+		BorrowedEvent::BeginComponent(name, _) => assert!(is_borrowed(name.get_ref())),
+		e => panic!("expected BeginComponent, got {:?}",e),
+	}
+	match p.next_event().unwrap().unwrap().into_owned(){
+		Event::Property(prop) => assert_eq!(prop.value,"2.0"),
+		e => panic!("expected Property, got {:?}",e),
+	}
+	assert!(matches!(p.next_event().unwrap(), Some(BorrowedEvent::EndComponent(..))));
+	assert!(p.next_event().unwrap().is_none());
+}
+
+//TESTS: SPANS
+
+fn span(start:usize, end:usize, start_line:u32, end_line:u32, column:usize)->Span{
+	Span{ start, end, start_line, end_line, column }
+}
+
+#[test]
+fn spans(){
+	let input="BEGIN:cal\r\nDESCRIPTION;LANG=de,\"en\":Test\r\nEND:cal\r\n";
+	let comp=BorrowedParser::new(input).next_component().unwrap().unwrap();
+	let cs=comp.span.unwrap();
+	assert_eq!(cs.begin, span(0,9,1,1,1));
+	assert_eq!(cs.end, span(42,49,3,3,1));
+
+	let ps=comp.properties[0].span.as_ref().unwrap();
+	assert_eq!(ps.line, span(11,40,2,2,1));
+	assert_eq!(ps.name, span(11,22,2,2,1));
+	assert_eq!(&input[ps.value.start..ps.value.end], "Test");
+	assert_eq!(ps.parameters.len(), 1);
+	assert_eq!(&input[ps.parameters[0].name.start..ps.parameters[0].name.end], "LANG");
+	assert_eq!(ps.parameters[0].values, vec![span(28,30,2,2,18), span(31,35,2,2,21)]);
+	assert_eq!(&input[31..35], "\"en\"");
+}
+
+#[test]
+fn spans_folded(){
+	let input="BEGIN:cal\r\nSUMMARY;X-A=b:Long\r\n  and\r\n\tfolded\r\nEND:cal\r\n";
+	let comp=Parser::new(Cursor::new(input)).next_component().unwrap().unwrap();
+	let ps=comp.properties[0].span.as_ref().unwrap();
+	assert_eq!(ps.line, span(11,45,2,4,1));
+	assert_eq!(ps.name, span(11,18,2,2,1));
+	//the value begins in line 2 and ends in line 4, the folds are part of the span.
+	assert_eq!(ps.value, span(25,45,2,4,15));
+	assert_eq!(&input[ps.value.start..ps.value.end], "Long\r\n  and\r\n\tfolded");
+	assert_eq!(comp.properties[0].value, "Long andfolded");
+	assert_eq!(comp.span.unwrap().end, span(47,54,5,5,1));
+}
+
+#[test]
+fn spans_fold_inside_name(){
+	let input="BEGIN:cal\r\nSUM\r\n MARY:x\r\nEND:c\r\n al\r\n";
+	let comp=BorrowedParser::new(input).next_component().unwrap().unwrap();
+	let ps=comp.properties[0].span.as_ref().unwrap();
+	assert_eq!(ps.name, span(11,21,2,3,1));
+	assert_eq!(ps.value, span(22,23,3,3,7));
+	assert_eq!(comp.span.unwrap().end, span(25,35,4,5,1));
+}
+
+#[test]
+fn spans_group(){
+	let input="BEGIN:VCARD\r\nitem1.EMAIL:a\r\nEND:VCARD\r\n";
+	let comp=BorrowedParser::new(input).next_component().unwrap().unwrap();
+	let ps=comp.properties[0].span.as_ref().unwrap();
+	assert_eq!(ps.group, Some(span(13,18,2,2,1)));
+	assert_eq!(ps.name, span(19,24,2,2,7));
+}
+
+#[test]
+fn spans_lenient(){
+	let input="BEGIN:cal\n\nX:y\r\n\nEND:cal";
+	let mut events=EventReader::new_with_options(Cursor::new(input), ParserOptions::lenient());
+	events.next_event().unwrap();
+	match events.next_event().unwrap().unwrap(){
+		Event::Property(p) => assert_eq!(p.span.unwrap().line, span(11,14,3,3,1)),
+		e => panic!("expected Property, got {:?}",e),
+	}
+	match events.next_event().unwrap().unwrap(){
+		Event::EndComponent(_, s) => assert_eq!(s, span(17,24,5,5,1)),
+		e => panic!("expected EndComponent, got {:?}",e),
+	}
+}
 
 //TESTS: EXPECTED ERRORS
 
@@ -173,6 +429,16 @@ fn wrong_linebreak(){
 	test_parse_error("\n\n", "line 1: expected CR ('\\r') before LF in empty line");
 }
 
+#[test]
+fn wrong_linebreak_missing_last_terminator(){
+	test_parse_error("BEGIN:comp\r\nEND:comp", "line 2: \texpected CR ('\\r') before LF: END:comp<HERE>\n");
+}
+
+#[test]
+fn lenient_error_line_number(){
+	test_parse_error_with_options("BEGIN:comp\n\n\nwas;x\n", ParserOptions::lenient(), "line 4: \texpected '=': was;x<HERE>\n");
+}
+
 #[test]
 fn wrong_line_begin(){
 	test_parse_error(":\r\n", "line 1: \texpected one or more alphanumerical characters or '-':  >:<\n");
@@ -260,6 +526,71 @@ fn wrong_comp_end2(){
 }
 
 
+#[test]
+fn error_kinds(){
+	let cases:&[(&str,ErrorKind)]=&[
+		(":\r\n",ErrorKind::InvalidName),
+		("BEGIN:co\r\ng.:x\r\n",ErrorKind::InvalidGroupedName),
+		("BEGIN\r\n",ErrorKind::ExpectedColon),
+		("BEGIN:\r\n",ErrorKind::EmptyComponentName),
+		("BEGIN:HI\u{2764}\r\n",ErrorKind::InvalidComponentName),
+		("BEGIN:co\r\nw :\r\n",ErrorKind::ExpectedColonOrSemicolon),
+		("BEGIN:co\r\nwas;\r\n",ErrorKind::EmptyParameterName),
+		("BEGIN:co\r\nwas;x\r\n",ErrorKind::ExpectedEquals),
+		("BEGIN:co\r\nwas;x=\"y\r\n",ErrorKind::UnterminatedQuotedValue),
+		("BEGIN:co\r\nwas;x=y\"\r\n",ErrorKind::ExpectedParameterSeparator),
+		("BEGIN:co\r\nwas:\u{fffd}\r\n",ErrorKind::InvalidCharacter),
+		("was:x\r\n",ErrorKind::ExpectedBegin),
+		("BEGIN:co\r\nend:x\r\n",ErrorKind::MismatchedEnd),
+		("BEGIN:co\n",ErrorKind::MissingCarriageReturn),
+		("BEGIN:co\r\n",ErrorKind::UnexpectedEof),
+	];
+	for (input,kind) in cases{
+		let e=Parser::new(Cursor::new(input.as_bytes())).next_component().unwrap_err();
+		assert_eq!(e.kind(), *kind, "{:?}", input);
+		assert_eq!(e.code(), kind.code());
+	}
+	let e=Parser::new(Cursor::new(b"BEGIN:co\r\nwas:\xff\r\n")).next_component().unwrap_err();
+	assert_eq!(e.kind(), ErrorKind::InvalidUtf8);
+}
+
+#[test]
+fn error_accessors(){
+	let e=Parser::new(Cursor::new("BEGIN:co\r\nwas;x=y;b=1:c\r\nwas;x\"y:z\r\n")).next_component().unwrap_err();
+	assert_eq!(e.line(), Some(3));
+	assert_eq!(e.column(), Some(6));
+	assert_eq!(e.offending_text(), Some("\""));
+	assert_eq!(e.expected(), vec!["="]);
+	assert_eq!(e.source_line(), Some("was;x\"y:z"));
+
+	let e=Parser::new(Cursor::new("BEGIN:co\r\nend:x\r\n")).next_component().unwrap_err();
+	assert_eq!((e.line(), e.column(), e.offending_text()), (Some(2), Some(5), Some("x")));
+	assert_eq!(e.expected(), vec!["END:CO"]);
+
+	let e=Parser::new(Cursor::new("BEGIN:co\r\n")).next_component().unwrap_err();
+	assert_eq!(e.expected(), vec!["END:CO"]);
+	assert_eq!(e.column(), None);
+}
+
+#[test]
+fn error_path(){
+	let input="BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nEND:VEVENT\r\nBEGIN:VTODO\r\nEND:VTODO\r\nBEGIN:VEVENT\r\nBEGIN:VALARM\r\nX;:y\r\n";
+	let e=Parser::new(Cursor::new(input)).next_component().unwrap_err();
+	let path=e.path().unwrap();
+	assert_eq!(path.to_string(), "VCALENDAR[0]/VEVENT[1]/VALARM[0]");
+	assert_eq!(path.iter().map(|s|s.line).collect::<Vec<_>>(), vec![1,6,7]);
+	expect_err(Err::<(),_>(e), "\tin VCALENDAR[0]/VEVENT[1]/VALARM[0] (BEGIN in line 7)\n");
+
+	//top-level components are counted across calls
+	let mut p=Parser::new(Cursor::new("BEGIN:a\r\nEND:a\r\nBEGIN:b\r\nEND:b\r\nBEGIN:A\r\n"));
+	p.next_component().unwrap();
+	p.next_component().unwrap();
+	assert_eq!(p.next_component().unwrap_err().path().unwrap().to_string(), "A[1]");
+
+	let e=Parser::new(Cursor::new("X:y\r\n")).next_component().unwrap_err();
+	assert!(e.path().unwrap().is_empty());
+}
+
 #[test]
 fn utf8_1(){
 	let st="BEGIN:co\r\nwas;=x";
@@ -270,13 +601,438 @@ fn utf8_1(){
 	data.push(b'\n');
 	let p = Parser::new(Cursor::new(data.as_slice()));
 	for obj in p{
-		match obj{
-			Err(e) => assert_eq!(e.to_string(),"invalid utf-8 sequence of 1 bytes from index 6"),
-			Ok(_) => {},
+		if let Err(e) = obj {
+			assert_eq!(e.to_string(),"line 2: \tinvalid UTF-8 at byte offset 16: invalid utf-8 sequence of 1 bytes from index 6\n\tin co[0] (BEGIN in line 1)\n")
 		}
 	};
 }
 
+//TESTS: ERROR RECOVERY
+
+#[test]
+fn recover_malformed_component(){
+	let input="BEGIN:a\r\nEND:a\r\nBEGIN:b\r\nwas;x\r\nBEGIN:inner\r\nEND:inner\r\nEND:b\r\nBEGIN:c\r\nEND:c\r\n";
+	let options=ParserOptions{recover_from_errors:true, ..ParserOptions::default()};
+	let mut p=Parser::new_with_options(Cursor::new(input), options);
+	assert_comp_equal(&p.next_component().unwrap().unwrap(),&c2("A"));
+	let e=p.next_component().unwrap_err();
+	assert_eq!(e.skipped_lines(), Some((3,7)));
+	assert_eq!(e.to_string(), "line 4: \texpected '=': was;x<HERE>\n\tin b[0] (BEGIN in line 3)\nlines 3-7: \tskipped to recover from the error\n");
+	assert_comp_equal(&p.next_component().unwrap().unwrap(),&c2("C"));
+	assert!(p.next_component().unwrap().is_none());
+}
+
+#[test]
+fn recover_missing_end(){
+	//the END line of the first VCARD is misspelled, recover on the next top-level component.
+	let input="BEGIN:vcard\r\nFN:a\r\nEND:vcart\r\nBEGIN:vcard\r\nFN:b\r\nEND:vcard\r\n";
+	let options=ParserOptions{recover_from_errors:true, ..ParserOptions::default()};
+	let results:Vec<_>=Parser::new_with_options(Cursor::new(input), options).collect();
+	assert_eq!(results.len(), 2);
+	assert_eq!(results[0].as_ref().unwrap_err().skipped_lines(), Some((1,3)));
+	assert_comp_equal(results[1].as_ref().unwrap(), &c("VCARD",vec![p2("FN","b")],vec![]));
+}
+
+#[test]
+fn recover_end_typo(){
+	let input="BEGIN:VCARD\r\nFN:a\r\nEND:VCART\r\nBEGIN:VCARD\r\nFN:b\r\nEND:VCARD\r\nBEGIN:VCARD\r\nFN:c\r\nEND:VCARD\r\n";
+	let options=ParserOptions{recover_from_errors:true, ..ParserOptions::default()};
+	let results:Vec<_>=Parser::new_with_options(Cursor::new(input), options).collect();
+	assert_eq!(results.len(), 3);
+	let e=results[0].as_ref().unwrap_err();
+	assert_eq!(e.kind(), ErrorKind::MismatchedEnd);
+	assert_eq!(e.skipped_lines(), Some((1,3)));
+	assert_comp_equal(results[1].as_ref().unwrap(), &c("VCARD",vec![p2("FN","b")],vec![]));
+	assert_comp_equal(results[2].as_ref().unwrap(), &c("VCARD",vec![p2("FN","c")],vec![]));
+}
+
+#[test]
+fn recover_nested_same_name(){
+	let input="BEGIN:a\r\nwas;x\r\nBEGIN:a\r\nEND:a\r\nEND:a\r\nBEGIN:b\r\nEND:b\r\n";
+	let options=ParserOptions{recover_from_errors:true, ..ParserOptions::default()};
+	let results:Vec<_>=Parser::new_with_options(Cursor::new(input), options).collect();
+	assert_eq!(results.len(), 2);
+	assert_eq!(results[0].as_ref().unwrap_err().skipped_lines(), Some((1,5)));
+	assert_comp_equal(results[1].as_ref().unwrap(), &c2("B"));
+}
+
+#[test]
+fn recover_top_level_garbage(){
+	let input="FN:a\r\nFN:b\r\nBEGIN:c\r\nEND:c\r\n:\r\n";
+	let options=ParserOptions{recover_from_errors:true, ..ParserOptions::default()};
+	let mut p=Parser::new_with_options(Cursor::new(input), options);
+	assert_eq!(p.next_component().unwrap_err().skipped_lines(), Some((1,2)));
+	assert_comp_equal(&p.next_component().unwrap().unwrap(),&c2("C"));
+	assert_eq!(p.next_component().unwrap_err().skipped_lines(), Some((5,5)));
+	assert!(p.next_component().unwrap().is_none());
+}
+
+#[test]
+fn recover_unexpected_eof(){
+	let input="BEGIN:a\r\nBEGIN:b\r\nFN:a\r\n";
+	let options=ParserOptions{recover_from_errors:true, ..ParserOptions::default()};
+	let results:Vec<_>=Parser::new_with_options(Cursor::new(input), options).collect();
+	assert_eq!(results.len(), 1);
+	assert_eq!(results[0].as_ref().unwrap_err().skipped_lines(), Some((1,3)));
+}
+
+//TESTS: LIMITS
+
+fn limited(limits:Limits)->ParserOptions{
+	ParserOptions{limits, ..ParserOptions::default()}
+}
+
+#[test]
+fn limit_depth(){
+	let input="BEGIN:a\r\nBEGIN:b\r\nBEGIN:c\r\nEND:c\r\nEND:b\r\nEND:a\r\n";
+	test_parse_error_with_options(input, limited(Limits{max_depth:Some(2), ..Limits::default()}),
+		"line 3: \tlimit exceeded: the nesting depth of components must not be larger than 2\n");
+	test_parse_with_options(input.as_bytes(), limited(Limits{max_depth:Some(3), ..Limits::default()}),
+		c("A",vec![],vec![c("B",vec![],vec![c2("C")])]));
+}
+
+#[test]
+fn limit_line_length(){
+	let options=limited(Limits{max_line_length:Some(10), ..Limits::default()});
+	test_parse_error_with_options("BEGIN:a\r\nSUMMARY:0123\r\nEND:a\r\n", options.clone(),
+		"line 2: \tlimit exceeded: the length of an unfolded line in bytes must not be larger than 10\n");
+	//the physical lines are short enough, but not the unfolded line
+	test_parse_error_with_options("BEGIN:a\r\nX:01\r\n 2345678\r\nEND:a\r\n", options.clone(),
+		"line 2: \tlimit exceeded: the length of an unfolded line in bytes must not be larger than 10\n");
+	test_parse_with_options(b"BEGIN:a\r\nX:01\r\n 234567\r\nEND:a\r\n", options, c("A",vec![p2("X","01234567")],vec![]));
+}
+
+#[test]
+fn limit_line_length_endless_line(){
+	//only the allowed number of bytes is read from an endless line
+	let input=Cursor::new("BEGIN:a\r\nX:").chain(std::io::repeat(b'a'));
+	let mut p=Parser::from_unbuffered_with_options(input, limited(Limits{max_line_length:Some(1000), ..Limits::default()}));
+	expect_err(p.next_component(), "line 2: \tlimit exceeded");
+}
+
+#[test]
+fn limit_properties(){
+	let input="BEGIN:a\r\nX:1\r\nBEGIN:b\r\nX:1\r\nX:2\r\nEND:b\r\nX:2\r\nitem.X:3\r\nEND:a\r\n";
+	test_parse_error_with_options(input, limited(Limits{max_properties:Some(2), ..Limits::default()}),
+		"line 8: \tlimit exceeded: the number of properties in a component must not be larger than 2\n");
+}
+
+#[test]
+fn limit_components(){
+	let input="BEGIN:a\r\nBEGIN:b\r\nEND:b\r\nEND:a\r\nBEGIN:c\r\nEND:c\r\n";
+	let mut p=Parser::new_with_options(Cursor::new(input), limited(Limits{max_components:Some(2), ..Limits::default()}));
+	assert_comp_equal(&p.next_component().unwrap().unwrap(), &c("A",vec![],vec![c2("B")]));
+	expect_err(p.next_component(), "line 5: \tlimit exceeded: the number of components must not be larger than 2\n");
+	assert!(p.next_component().unwrap().is_none());
+}
+
+#[test]
+fn limit_total_bytes(){
+	let input="BEGIN:a\r\nEND:a\r\nBEGIN:b\r\nEND:b\r\n";
+	let mut p=BorrowedParser::new_with_options(input, limited(Limits{max_total_bytes:Some(20), ..Limits::default()}));
+	assert_comp_equal(&p.next_component().unwrap().unwrap().into_owned(), &c2("A"));
+	expect_err(p.next_component(), "line 3: \tlimit exceeded: the size of the input in bytes must not be larger than 20\n");
+	assert!(p.next_component().unwrap().is_none());
+}
+
+#[test]
+fn limit_stops_recovery(){
+	let input="BEGIN:a\r\nX:1\r\nX:2\r\nEND:a\r\nBEGIN:b\r\nEND:b\r\n";
+	let options=ParserOptions{
+		recover_from_errors:true,
+		limits:Limits{max_properties:Some(1), ..Limits::default()},
+		..ParserOptions::default()
+	};
+	let results:Vec<_>=Parser::new_with_options(Cursor::new(input), options.clone()).collect();
+	assert_eq!(results.len(), 1);
+	assert!(results[0].as_ref().unwrap_err().skipped_lines().is_none());
+
+	//limits are also checked while skipping lines
+	let input="BEGIN:a\r\nX;:1\r\nX:0123456789\r\nEND:a\r\nBEGIN:b\r\nEND:b\r\n";
+	let options=ParserOptions{limits:Limits{max_line_length:Some(10), ..Limits::default()}, ..options};
+	let results:Vec<_>=Parser::new_with_options(Cursor::new(input), options).collect();
+	assert_eq!(results.len(), 1);
+	expect_err(results[0].as_ref(), "line 3: \tlimit exceeded");
+}
+
+//TESTS: DIAGNOSTICS
+
+fn diagnose(input:&[u8])->(Vec<crate::Component>,Vec<Diagnostic>){
+	let options=ParserOptions{collect_diagnostics:true, ..ParserOptions::default()};
+	let mut p=BorrowedParser::new_with_options(input, options);
+	let components=p.by_ref().map(|c|c.unwrap().into_owned()).collect();
+	(components,p.take_diagnostics())
+}
+
+#[test]
+fn diagnostics_malformed_lines(){
+	let (components,diagnostics)=diagnose(b"FN:a\r\nBEGIN:a\r\nwas;x\r\nFN:b\r\nEND:a\r\n");
+	assert_eq!(components.len(), 1);
+	assert_comp_equal(&components[0], &c("A",vec![p2("FN","b")],vec![]));
+	assert_eq!(diagnostics.len(), 2);
+	assert_eq!(diagnostics[0].severity, Severity::Error);
+	assert_eq!(diagnostics[0].kind, ErrorKind::ExpectedBegin);
+	assert_eq!(diagnostics[0].span.start_line, 1);
+	assert_eq!(diagnostics[0].to_string(), "error[expected-begin]: line 1: \texpected BEGIN:  >FN< :a");
+	assert_eq!(diagnostics[1].span, Span{start:20,end:20,start_line:3,end_line:3,column:6});
+}
+
+#[test]
+fn diagnostics_crlf(){
+	let (components,diagnostics)=diagnose(b"BEGIN:a\nFN:b\r\nEND:a");
+	assert_comp_equal(&components[0], &c("A",vec![p2("FN","b")],vec![]));
+	assert_eq!(diagnostics.iter().map(|d|(d.severity,d.code(),d.span.start_line)).collect::<Vec<_>>(),
+		vec![(Severity::Warning,"missing-carriage-return",1),(Severity::Warning,"missing-carriage-return",3)]);
+	assert_eq!(diagnostics[0].span.start, 7);
+}
+
+#[test]
+fn diagnostics_missing_end(){
+	//the END line of b is missing, END:c is wrong and skipped
+	let (components,diagnostics)=diagnose(b"BEGIN:a\r\nBEGIN:b\r\nFN:b\r\nEND:c\r\nEND:a\r\nBEGIN:d\r\n");
+	assert_eq!(components.len(), 2);
+	assert_comp_equal(&components[0], &c("A",vec![],vec![c("B",vec![p2("FN","b")],vec![])]));
+	assert_comp_equal(&components[1], &c2("D"));
+	assert_eq!(diagnostics.iter().map(|d|(d.code(),d.span.start_line)).collect::<Vec<_>>(),
+		vec![("mismatched-end",4),("mismatched-end",5),("unexpected-eof",6)]);
+	assert_eq!(diagnostics[2].message, "line 7: \tunexpected end of file or stream, expected END:D\n\tin d[0] (BEGIN in line 6)");
+	assert_eq!(components[1].span.unwrap().end.start, 45);
+}
+
+#[test]
+fn diagnostics_utf8(){
+	let (components,diagnostics)=diagnose(b"BEGIN:a\r\nFN:\xff\r\nEND:a\r\n");
+	assert_comp_equal(&components[0], &c2("A"));
+	assert_eq!(diagnostics.len(), 1);
+	assert_eq!(diagnostics[0].code(), "invalid-utf8");
+	assert_eq!(diagnostics[0].span.start_line, 2);
+}
+
+#[test]
+fn diagnostics_non_ascii_context(){
+	//the context of the message must not be cut within a character
+	let (components,diagnostics)=diagnose("BEGIN:a\r\nX;AB=\"äääääääääää\"\r\nEND:a\r\n".as_bytes());
+	assert_comp_equal(&components[0], &c2("A"));
+	assert_eq!(diagnostics.len(), 1);
+	assert!(diagnostics[0].message.contains("äää"));
+}
+
+#[test]
+fn diagnostics_limits(){
+	let options=ParserOptions{collect_diagnostics:true, limits:Limits{max_depth:Some(1), ..Limits::default()}, ..ParserOptions::default()};
+	let mut p=Parser::new_with_options(Cursor::new("BEGIN:a\r\nBEGIN:b\r\n"), options);
+	expect_err(p.next_component(), "line 2: \tlimit exceeded");
+	assert!(p.take_diagnostics().is_empty());
+}
+
+//TESTS: PUSH PARSER
+
+#[test]
+fn push_chunks(){
+	let input="BEGIN:comp\r\nFEATURE:Conten\r\n t:'!,;.'\r\nBEGIN:inner\r\nX:\u{2764}\r\nEND:inner\r\nEND:Comp\r\nBEGIN:porp\r\nEND:poRp\r\n";
+	let expected=[c("COMP",vec![p2("FEATURE","Content:'!,;.'")],vec![c("INNER",vec![p2("X","\u{2764}")],vec![])]),c2("PORP")];
+	for size in 1..8{
+		let mut p=PushParser::new();
+		let mut got=vec![];
+		for chunk in input.as_bytes().chunks(size){
+			p.feed(chunk);
+			while let Some(c)=p.next_component().unwrap(){
+				got.push(c);
+			}
+		}
+		assert!(!p.is_finished());
+		p.finish();
+		while let Some(c)=p.next_component().unwrap(){
+			got.push(c);
+		}
+		assert!(p.is_finished());
+		assert_eq!(got.len(), 2, "chunk size {}", size);
+		assert_comp_equal(&got[0], &expected[0]);
+		assert_comp_equal(&got[1], &expected[1]);
+	}
+}
+
+#[test]
+fn push_lossless(){
+	let input="BEGIN:comp\r\nFEATURE:Conten\r\n t\r\nEND:Comp\r\nBEGIN:porp\nEND:poRp";
+	let mut p=PushParser::new_with_options(ParserOptions{lossless:true, ..ParserOptions::lenient()});
+	let mut out=String::new();
+	for chunk in input.as_bytes().chunks(3){
+		p.feed(chunk);
+		while let Some(c)=p.next_component().unwrap(){
+			out+=&c.encode_to_string_with_options(&EncoderOptions::lossless());
+		}
+	}
+	p.finish();
+	while let Some(c)=p.next_component().unwrap(){
+		out+=&c.encode_to_string_with_options(&EncoderOptions::lossless());
+	}
+	assert_eq!(out, input.to_string()+"\r\n");
+}
+
+#[test]
+fn push_events(){
+	let mut p=PushParser::new();
+	p.feed(b"BEGIN:a\r\nX:1\r");
+	assert!(matches!(p.next_event().unwrap(), Some(Event::BeginComponent(..))));
+	//the property may still be folded
+	assert!(p.next_event().unwrap().is_none());
+	p.feed(b"\n");
+	assert!(p.next_event().unwrap().is_none());
+	p.feed(b" 2\r\nE");
+	match p.next_event().unwrap(){
+		Some(Event::Property(prop)) => assert_eq!(prop.value, "12"),
+		e => panic!("expected a property, got {:?}", e),
+	}
+	assert!(p.next_event().unwrap().is_none());
+	assert_eq!(p.depth(), 1);
+	p.feed(b"ND:a\r\n");
+	p.finish();
+	assert!(matches!(p.next_event().unwrap(), Some(Event::EndComponent(..))));
+	assert!(p.next_event().unwrap().is_none());
+	assert!(p.is_finished());
+}
+
+#[test]
+fn push_mixed_events_and_components(){
+	let mut p=PushParser::new();
+	p.feed(b"BEGIN:a\r\nX:1\r\nBEGIN:b\r\nEND:b\r\nEND:a\r\nBEGIN:c\r\nY:2\r\nEND:c\r\nBEGIN:d\r\nEND:d\r\n");
+	p.finish();
+	//a component begun with events is completed by next_component
+	assert!(matches!(p.next_event().unwrap(), Some(Event::BeginComponent(..))));
+	assert!(matches!(p.next_event().unwrap(), Some(Event::Property(..))));
+	assert_comp_equal(&p.next_component().unwrap().unwrap(), &c("A",vec![p2("X","1")],vec![c2("B")]));
+	//and the other way around
+	assert!(matches!(p.next_event().unwrap(), Some(Event::BeginComponent(..))));
+	assert!(matches!(p.next_event().unwrap(), Some(Event::Property(..))));
+	assert!(matches!(p.next_event().unwrap(), Some(Event::EndComponent(..))));
+	assert_comp_equal(&p.next_component().unwrap().unwrap(), &c2("D"));
+	assert!(p.next_component().unwrap().is_none());
+}
+
+#[test]
+fn push_events_after_partial_component(){
+	let mut p=PushParser::new();
+	p.feed(b"BEGIN:a\r\nX:1\r\n");
+	assert!(p.next_component().unwrap().is_none());
+	p.feed(b"Y:2\r\nEND:a\r\n");
+	p.finish();
+	assert!(matches!(p.next_event().unwrap(), Some(Event::Property(..))));
+	assert_comp_equal(&p.next_component().unwrap().unwrap(), &c("A",vec![p2("X","1"),p2("Y","2")],vec![]));
+	assert!(p.next_component().unwrap().is_none());
+	assert!(p.is_finished());
+}
+
+#[test]
+fn push_component_before_next_line(){
+	let mut p=PushParser::new();
+	p.feed(b"BEGIN:a\r\nEND:a\r\n");
+	assert!(p.next_component().unwrap().is_none());
+	//the first byte of the next line shows that the END line is complete
+	p.feed(b"B");
+	assert_comp_equal(&p.next_component().unwrap().unwrap(), &c2("A"));
+	p.feed(b"EGIN:b\r\n");
+	p.finish();
+	expect_err(p.next_component(), "line 4: \tunexpected end of file or stream, expected END:B");
+	assert!(p.next_component().unwrap().is_none());
+}
+
+#[test]
+fn push_vcard21_soft_breaks(){
+	let mut p=PushParser::new_with_options(ParserOptions::vcard21());
+	p.feed(b"BEGIN:VCARD\nNOTE;QUOTED-PRINTABLE:a=\n");
+	p.feed(b"b\nEND:VCARD\nB");
+	assert_comp_equal(&p.next_component().unwrap().unwrap(), &c("VCARD",vec![p2("NOTE","ab")],vec![]));
+}
+
+//TESTS: DECODING
+
+fn decoding(decoding:Decoding)->ParserOptions{
+	ParserOptions{decoding, ..ParserOptions::default()}
+}
+
+#[test]
+fn decoding_strict(){
+	let e=Parser::new(Cursor::new(b"BEGIN:co\r\nX:a\r\n b\xe4c\r\nEND:co\r\n")).next_component().unwrap_err();
+	assert_eq!(e.kind(), ErrorKind::InvalidUtf8);
+	assert_eq!((e.line(),e.column(),e.offset()), (Some(2),Some(5),Some(17)));
+	expect_err(Err::<(),_>(e), "line 2: \tinvalid UTF-8 at byte offset 17: invalid utf-8 sequence of 1 bytes from index 4");
+}
+
+#[test]
+fn decoding_lossy(){
+	test_parse_with_options(b"BEGIN:co\r\nX;P=\xff:a\xe4\r\nEND:co\r\n", decoding(Decoding::Lossy),
+		c("CO",vec![p("X","a\u{fffd}",pm(vec![("P",vec!["\u{fffd}"])]))],vec![]));
+	//names can still not contain the replacement character
+	test_parse_error_with_options("BEGIN:co\r\nX\u{fffd}:a\r\nEND:co\r\n", decoding(Decoding::Lossy), "line 2: \texpected ':' or ';': X >\u{fffd}< :a");
+}
+
+#[test]
+fn decoding_fallback(){
+	test_parse_with_options(b"BEGIN:co\r\nX:M\xfcller \x80\r\nY;CHARSET=WINDOWS-1252:\x80\r\nZ:\xc3\xa4\r\nEND:co\r\n", decoding(Decoding::Fallback(Charset::Latin1)),
+		c("CO",vec![p2("X","Müller \u{80}"),p("Y","€",pm(vec![("CHARSET",vec!["WINDOWS-1252"])])),p2("Z","ä")],vec![]));
+}
+
+#[test]
+fn decoding_diagnostics(){
+	let options=ParserOptions{collect_diagnostics:true, ..decoding(Decoding::Lossy)};
+	let mut p=BorrowedParser::new_with_options(b"BEGIN:co\r\nX:ab\xff\r\nEND:co\r\n", options);
+	assert_comp_equal(&p.next_component().unwrap().unwrap().into_owned(), &c("CO",vec![p2("X","ab\u{fffd}")],vec![]));
+	let diagnostics=p.take_diagnostics();
+	assert_eq!(diagnostics.iter().map(|d|(d.severity,d.code())).collect::<Vec<_>>(), vec![(Severity::Warning,"invalid-utf8")]);
+	assert_eq!(diagnostics[0].span, Span{start:14,end:15,start_line:2,end_line:2,column:5});
+}
+
+#[test]
+fn byte_order_mark(){
+	let input=b"\xef\xbb\xbfBEGIN:co\r\nEND:co\r\n";
+	test_parse_bytes(input, c2("CO"));
+	let component=BorrowedParser::new(input).next_component().unwrap().unwrap();
+	assert_eq!(component.span.unwrap().begin, Span{start:3,end:11,start_line:1,end_line:1,column:1});
+	//only at the beginning of the input
+	test_parse_error("BEGIN:co\r\n\u{feff}X:a\r\nEND:co\r\n", "line 2: \texpected one or more alphanumerical characters or '-':  >\u{feff}< X:a");
+}
+
+//TESTS: VCARD 2.1
+
+fn vcard21()->ParserOptions{
+	ParserOptions::vcard21()
+}
+
+#[test]
+fn vcard21_bare_parameters(){
+	test_parse_with_options(b"BEGIN:VCARD\r\nTEL;HOME;VOICE:123\r\nTEL;WORK;X=a;FAX;BASE64:456\r\nEND:VCARD\r\n", vcard21(),
+		c("VCARD",vec![
+			p("TEL","123",pm(vec![("TYPE",vec!["HOME","VOICE"])])),
+			p("TEL","456",pm(vec![("TYPE",vec!["WORK"]),("X",vec!["a"]),("TYPE",vec!["FAX"]),("ENCODING",vec!["BASE64"])])),
+		],vec![]));
+	//bare parameters are only accepted in the vCard 2.1 dialect
+	test_parse_error("BEGIN:VCARD\r\nTEL;HOME:123\r\nEND:VCARD\r\n", "line 2: \texpected '=': TEL;HOME >:< 123");
+}
+
+#[test]
+fn vcard21_quoted_printable(){
+	let input=b"BEGIN:VCARD\nNOTE;ENCODING=QUOTED-PRINTABLE;CHARSET=UTF-8:a=C3=A4=0D=0A=\n b=3D=\r\nc\nADR;HOME;QUOTED-PRINTABLE:;;Stra=DFe;\nEND:VCARD\n";
+	test_parse_with_options(input, vcard21(),
+		c("VCARD",vec![
+			p2("NOTE","aä\r\n b=c"),
+			p("ADR",";;Stra\u{df}e;",pm(vec![("TYPE",vec!["HOME"])])),
+		],vec![]));
+	let mut parser=BorrowedParser::new_with_options(&input[..], vcard21());
+	let component=parser.next_component().unwrap().unwrap();
+	let span=component.properties[0].span.as_ref().unwrap();
+	assert_eq!((span.line.start_line,span.line.end_line,span.value.end_line),(2,4,4));
+	assert!(span.parameters.is_empty());
+}
+
+#[test]
+fn vcard21_charset(){
+	test_parse_with_options(b"BEGIN:VCARD\r\nN;CHARSET=ISO-8859-1:M\xfcller;J\xf6rg\r\nFN;CHARSET=WINDOWS-1252;ENCODING=QUOTED-PRINTABLE:=93J=F6rg=94\r\nEND:VCARD\r\n", vcard21(),
+		c("VCARD",vec![p2("N","Müller;Jörg"),p2("FN","“Jörg”")],vec![]));
+	test_parse_error_with_options("BEGIN:VCARD\r\nN;CHARSET=SHIFT_JIS:a\r\nEND:VCARD\r\n", vcard21(),
+		"line 2: \tunsupported character set: N;CHARSET= >SHIFT_JIS< :a");
+}
+
 //wrong param id
 
 //an example which was panicking on a fuzz
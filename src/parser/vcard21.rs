@@ -0,0 +1,137 @@
+//Helpers for the vCard 2.1 dialect, which encodes values with quoted-printable (RFC 2045, section 6.7).
+
+use crate::parser::{Decoding, Dialect, ParserOptions};
+use crate::parser::charset::{declared_charset, Charset};
+use crate::parser::line_lexer::{ItemType, LineLexer};
+
+//the encodings which are written as bare parameters, they are parameters of ENCODING instead of TYPE.
+pub(crate) const ENCODINGS: [&str; 4] = ["7BIT", "8BIT", "QUOTED-PRINTABLE", "BASE64"];
+
+//decode_quoted_printable decodes "=XX" sequences into bytes. Soft line breaks have to be removed before,
+// invalid sequences are kept as they are.
+pub fn decode_quoted_printable(encoded: &str) -> Vec<u8> {
+	let bytes = encoded.as_bytes();
+	let mut out = Vec::with_capacity(bytes.len());
+	let mut i = 0;
+	while i < bytes.len() {
+		//from_str_radix would accept a sign, so both bytes are checked before
+		let hex = bytes.get(i + 1..i + 3)
+				.filter(|h| h.iter().all(u8::is_ascii_hexdigit))
+				.and_then(|h| u8::from_str_radix(std::str::from_utf8(h).ok()?, 16).ok());
+		match (bytes[i], hex) {
+			(b'=', Some(b)) => {
+				out.push(b);
+				i += 3;
+			}
+			(b, _) => {
+				out.push(b);
+				i += 1;
+			}
+		}
+	}
+	out
+}
+
+//encode_quoted_printable encodes the UTF-8 bytes of the text, leaving printable ASCII characters (except
+// '=') as they are. The encoded text does not contain soft line breaks.
+pub fn encode_quoted_printable(text: &str) -> String {
+	let mut out = String::with_capacity(text.len());
+	for &b in text.as_bytes() {
+		if (b' '..=b'~').contains(&b) && b != b'=' {
+			out.push(b as char);
+		} else {
+			out.push_str(&format!("={:02X}", b));
+		}
+	}
+	out
+}
+
+//needs_quoted_printable returns whether the value can only be written with the quoted-printable encoding
+// in vCard 2.1, which is the case for line breaks and non-ASCII characters.
+pub(crate) fn needs_quoted_printable(value: &str) -> bool {
+	value.bytes().any(|b| !(b' '..=b'~').contains(&b))
+}
+
+//parameters lexes the line and returns the names and values of its parameters (bare ones with the name
+// they get in vCard 2.1). It returns None if the line can not be lexed or ends before its value, so a '='
+// within the parameters is never taken for a soft line break. Only the part before the value has to be
+// valid UTF-8.
+pub(crate) fn parameters(line: &[u8]) -> Option<Vec<(String, String)>> {
+	let line = String::from_utf8_lossy(line);
+	let options = ParserOptions { dialect: Dialect::VCard21, decoding: Decoding::Lossy, ..ParserOptions::default() };
+	let mut lexer = LineLexer::new(&line, &options);
+	let mut params = Vec::new();
+	let mut name = None;
+	while let Some(item) = lexer.next_item() {
+		match item.typ {
+			ItemType::Id => name = Some(item.val.into_owned()),
+			ItemType::ParamValue => params.push((name.clone()?, item.val.into_owned())),
+			ItemType::PropValue => return Some(params),
+			ItemType::Error(_) => return None,
+			_ => {}
+		}
+	}
+	None
+}
+
+//parameter returns the first value of the parameter with the name (case-insensitive).
+pub(crate) fn parameter<'p>(params: &'p [(String, String)], name: &str) -> Option<&'p str> {
+	params.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+}
+
+//is_quoted_printable returns whether the unfolded line declares its value to be quoted-printable encoded.
+pub(crate) fn is_quoted_printable(line: &[u8]) -> bool {
+	parameters(line).is_some_and(|params| params.iter()
+			.any(|(n, v)| n.eq_ignore_ascii_case("ENCODING") && v.eq_ignore_ascii_case("QUOTED-PRINTABLE")))
+}
+
+//has_soft_break returns whether the value of the line is continued on the next physical line.
+pub(crate) fn has_soft_break(line: &[u8]) -> bool {
+	line.last() == Some(&b'=') && is_quoted_printable(line)
+}
+
+//decode_line converts a line which declares a character set other than UTF-8 to UTF-8, so that it can be lexed.
+// It returns None if the line does not need to be converted or the character set is not supported.
+pub(crate) fn decode_line(line: &[u8]) -> Option<String> {
+	//most lines don't mention a character set, they are not lexed
+	if !line.windows(7).any(|w| w.eq_ignore_ascii_case(b"CHARSET")) {
+		return None;
+	}
+	match declared_charset(line)? {
+		Charset::Utf8 => None,
+		charset => charset.decode(line.to_vec()).ok(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::parser::vcard21::*;
+
+	#[test]
+	fn quoted_printable() {
+		assert_eq!(decode_quoted_printable("a=3Db=C3=A4=0D=0Ac"), "a=bä\r\nc".as_bytes());
+		assert_eq!(decode_quoted_printable("=e4=4"), b"\xe4=4");
+		assert_eq!(decode_quoted_printable("=XY="), b"=XY=");
+		assert_eq!(decode_quoted_printable("a=+Fb"), b"a=+Fb");
+		assert_eq!(decode_quoted_printable("a=-1"), b"a=-1");
+		assert_eq!(decode_quoted_printable("a="), b"a=");
+		assert_eq!(encode_quoted_printable("a=bä\r\nc"), "a=3Db=C3=A4=0D=0Ac");
+		assert_eq!(encode_quoted_printable(" ~"), " ~");
+	}
+
+	#[test]
+	fn lines() {
+		assert_eq!(decode_line(b"N;CHARSET=ISO-8859-1:M\xfcller"), Some("N;CHARSET=ISO-8859-1:Müller".to_string()));
+		assert_eq!(decode_line(b"N;charset=utf-8:M\xc3\xbcller"), None);
+		assert_eq!(decode_line(b"N:CHARSET=ISO-8859-1"), None);
+		assert!(has_soft_break(b"NOTE;ENCODING=QUOTED-PRINTABLE:a="));
+		assert!(has_soft_break(b"NOTE;quoted-printable:a="));
+		assert!(!has_soft_break(b"NOTE:a="));
+		assert!(!has_soft_break(b"NOTE;ENCODING=QUOTED-PRINTABLE:a"));
+		assert!(has_soft_break(b"NOTE;X=\"a:b\";ENCODING=QUOTED-PRINTABLE:a="));
+		assert!(!has_soft_break(b"NOTE;X-ENCODING=QUOTED-PRINTABLE:a="));
+		assert!(!has_soft_break(b"NOTE;X=\"QUOTED-PRINTABLE\":a="));
+		//the line ends within the parameters
+		assert!(!has_soft_break(b"NOTE;ENCODING=QUOTED-PRINTABLE;CHARSET="));
+	}
+}
@@ -0,0 +1,58 @@
+use crate::{Name, Parameters, Property};
+
+//The physical lines of parsed properties and components, which are kept if `ParserOptions::lossless` is set.
+// They contain the line endings and fold positions of the input, as well as any empty lines which were skipped
+// before the line. Together with the lines, the parsed values are kept, so that the encoder can tell whether
+// the property or component was modified since (see `EncoderOptions::lossless`).
+
+//RawProperty holds the lines a property was parsed from.
+#[derive(Clone, Debug)]
+pub struct RawProperty {
+	pub lines: Vec<u8>,
+	group: Option<String>,
+	name: String,
+	value: String,
+	parameters: Parameters,
+}
+
+impl RawProperty {
+	pub(crate) fn new(lines: Vec<u8>, property: &Property) -> Self {
+		RawProperty {
+			lines,
+			group: property.group.as_ref().map(|g| g.to_string()),
+			name: property.name.to_string(),
+			value: property.value.clone(),
+			parameters: property.parameters.clone(),
+		}
+	}
+
+	//is_unmodified returns whether the property still has the parsed values, including the spelling of its names.
+	pub fn is_unmodified(&self, property: &Property) -> bool {
+		self.value == property.value
+				&& self.name == property.name.as_str()
+				&& self.group.as_deref() == property.group.as_ref().map(Name::as_str)
+				&& self.parameters.len() == property.parameters.len()
+				&& self.parameters.iter().zip(&property.parameters)
+						.all(|((a, a_values), (b, b_values))| a.as_str() == b.as_str() && a_values == b_values)
+	}
+}
+
+//RawComponent holds the BEGIN and END lines of a component.
+#[derive(Clone, Debug)]
+pub struct RawComponent {
+	pub begin: Vec<u8>,
+	//None until the component is ended, or if the END line was missing and inserted by the parser.
+	pub end: Option<Vec<u8>>,
+	name: String,
+}
+
+impl RawComponent {
+	pub(crate) fn new(begin: Vec<u8>, name: &str) -> Self {
+		RawComponent { begin, end: None, name: name.to_string() }
+	}
+
+	//is_unmodified returns whether the component still has the parsed name, in the same spelling.
+	pub fn is_unmodified(&self, name: &str) -> bool {
+		self.name == name
+	}
+}
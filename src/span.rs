@@ -0,0 +1,50 @@
+//Span describes where some text was found in the parsed input. Offsets are byte offsets from the start of
+// the input, lines are the physical (folded) lines, counted from 1. If the text was folded, it starts
+// in start_line and ends in end_line, the fold characters (CRLF and the following space) are then part of the span.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+	//offset of the first byte
+	pub start: usize,
+	//offset after the last byte
+	pub end: usize,
+	pub start_line: u32,
+	pub end_line: u32,
+	//the column of the first byte in start_line, counted in bytes from 1
+	pub column: usize,
+}
+
+impl Span {
+	pub fn len(&self) -> usize {
+		self.end - self.start
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.start == self.end
+	}
+}
+
+//ComponentSpan contains the spans of the BEGIN and END lines of a component.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ComponentSpan {
+	pub begin: Span,
+	pub end: Span,
+}
+
+//PropertySpan contains the span of the whole property line and of its parts.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PropertySpan {
+	pub line: Span,
+	//the span of the group (without the '.'), if the property has one
+	pub group: Option<Span>,
+	pub name: Span,
+	pub value: Span,
+	//the spans of all parameters in the order they appear in the line, one for every entry of the ParameterList
+	pub parameters: Vec<ParameterSpan>,
+}
+
+//ParameterSpan contains the span of a parameter name and of each of its values (including quotes).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ParameterSpan {
+	pub name: Span,
+	pub values: Vec<Span>,
+}
@@ -2,7 +2,7 @@
 
 use crate::Component;
 use std::io::Cursor;
-use crate::{Parser,Property,Parameters};
+use crate::{BorrowedParser,Parser,ParserOptions,Property,Parameters};
 use core::fmt::Debug;
 use std::error::Error;
 
@@ -11,21 +11,36 @@ pub fn test_parse(input:&str, expected:Component){
 }
 
 pub fn test_parse_bytes(input:&[u8], expected:Component){
+	test_parse_with_options(input, ParserOptions::default(), expected);
+}
+
+pub fn test_parse_with_options(input:&[u8], options:ParserOptions, expected:Component){
 	let x=Cursor::new(input);
-	let mut p=Parser::new(x);
+	let mut p=Parser::new_with_options(x, options.clone());
 	let got=p.next_component().unwrap().unwrap();
 	assert_comp_equal(&got,&expected);
 	if let Some(x) = p.next_component().unwrap(){
 		panic!("expected EOF, but got:\n{:#?}",x)
 	}
+
+	//the borrowed parser has to produce the same result
+	let mut p=BorrowedParser::new_with_options(input, options);
+	let got=p.next_component().unwrap().unwrap().into_owned();
+	assert_comp_equal(&got,&expected);
+	if let Some(x) = p.next_component().unwrap(){
+		panic!("expected EOF, but got:\n{:#?}",x)
+	}
 }
 
 //Convenience functions for quickly setting up Components, Properties and Parameters
 pub fn c(name:&str, props:Vec<Property>, comps:Vec<Component>)->Component{
 	Component{
-		name: name.to_string(),
+		name: name.into(),
 		properties: props,
 		sub_components: comps,
+		span: None,
+		order: vec![],
+		raw: None,
 	}
 }
 pub fn c2(name:&str)->Component{
@@ -34,10 +49,13 @@ pub fn c2(name:&str)->Component{
 
 pub fn p(name:&str, value:&str, param:Parameters) ->Property{
 	Property{
-		name: name.to_string(),
+		group: None,
+		name: name.into(),
 		value: value.to_string(),
 		parameters: param,
-		old_line: None
+		old_line: None,
+		span: None,
+		raw: None,
 	}
 }
 
@@ -48,7 +66,7 @@ pub fn p2(name:&str, value:&str) ->Property{
 pub fn pm(vals:Vec<(&str,Vec<&str>)>)->Parameters{
 	let mut out=Parameters::new();
 	for (k,v) in vals{
-		out.insert(k.to_string(),v.iter().map(|&s|s.to_string()).collect());
+		out.push(k.to_string(),v.iter().map(|&s|s.to_string()).collect());
 	}
 	out
 }
@@ -68,34 +86,30 @@ pub fn assert_comp_equal(a:&Component,b:&Component){
 }
 
 fn assert_prop_equal(a:&Property,b:&Property){
+	assert_eq!(a.group,b.group,"property groups");
 	assert_eq!(a.name,b.name,"property names");
 	assert_eq!(a.value,b.value,"property values");
-	assert_eq!(a.parameters.len(),b.parameters.len(),"parameter counts");
-
-	for (a_p,a_values) in &a.parameters{
-		for a_val in a_values{
-			assert!(b.parameters.get(a_p).unwrap().contains(a_val))
-		}
-	}
-	//this tested if all values of a are in b. Now we test the reverse
-	for (b_p,b_values) in &b.parameters{
-		for b_val in b_values{
-			assert!(b.parameters.get(b_p).unwrap().contains(b_val))
-		}
-	}
-
+	assert_eq!(a.parameters,b.parameters,"parameters");
 }
 
 //fn test_error<'a,P:Pattern<'a>>(input:&str, error:P){
 pub fn test_parse_error(input:&str, error:&str){
+	test_parse_error_with_options(input, ParserOptions::default(), error);
+}
+
+pub fn test_parse_error_with_options(input:&str, options:ParserOptions, error:&str){
 	let x=Cursor::new(input);
-	let mut p=Parser::new(x);
+	let mut p=Parser::new_with_options(x, options.clone());
 
 
 	expect_err(p.next_component(),error);
 
 	//drain the parser. This should not panic!
 	for _obj in p {};
+
+	let mut p=BorrowedParser::new_with_options(input, options);
+	expect_err(p.next_component(),error);
+	for _obj in p {};
 }
 
 pub fn expect_err<R:Debug, E:Error>(res:Result<R,E>, msg:&str) {
@@ -105,3 +119,9 @@ pub fn expect_err<R:Debug, E:Error>(res:Result<R,E>, msg:&str) {
 		Ok(c) => panic!("Expected an error, but got:{:?}",c)
 	}
 }
+
+pub fn pg(group:&str, name:&str, value:&str, param:Parameters) ->Property{
+	let mut out=p(name,value,param);
+	out.group=Some(group.into());
+	out
+}
@@ -0,0 +1,155 @@
+//Escaping of property values of the type TEXT as defined in RFC 5545 (section 3.3.11) and
+// RFC 6350 (section 3.4): backslashes, semicolons, commas and line breaks are escaped with a backslash.
+
+pub fn unescape_text(escaped: &str) -> String {
+	let mut out = String::with_capacity(escaped.len());
+	let mut chars = escaped.chars();
+	while let Some(c) = chars.next() {
+		if c != '\\' {
+			out.push(c);
+			continue;
+		}
+		match chars.next() {
+			Some('n') | Some('N') => out.push('\n'),
+			Some(c @ ('\\' | ';' | ',')) => out.push(c),
+			//keep unknown escape sequences and a trailing backslash as they are
+			Some(c) => {
+				out.push('\\');
+				out.push(c);
+			}
+			None => out.push('\\'),
+		}
+	}
+	out
+}
+
+pub fn escape_text(unescaped: &str) -> String {
+	let mut out = String::with_capacity(unescaped.len());
+	let mut chars = unescaped.chars().peekable();
+	while let Some(c) = chars.next() {
+		match c {
+			'\\' | ';' | ',' => {
+				out.push('\\');
+				out.push(c);
+			}
+			//all line breaks (CRLF, CR and LF) are encoded as \n
+			'\r' => {
+				chars.next_if_eq(&'\n');
+				out.push_str("\\n");
+			}
+			'\n' => out.push_str("\\n"),
+			c => out.push(c),
+		}
+	}
+	out
+}
+
+//split_structured splits a structured value (like the one of N or ADR) at unescaped semicolons into its
+// components and every component at unescaped commas into its values, which are unescaped. A value without
+// separators is a single component with a single value, a list (like the one of CATEGORIES) a single component.
+// Every component has at least one value, so an empty component is split into a single empty value.
+pub fn split_structured(escaped: &str) -> Vec<Vec<String>> {
+	let mut out = vec![vec![]];
+	let mut start = 0;
+	let mut chars = escaped.char_indices();
+	while let Some((i, c)) = chars.next() {
+		match c {
+			//the escaped character is never a separator
+			'\\' => {
+				chars.next();
+			}
+			',' | ';' => {
+				out.last_mut().unwrap().push(unescape_text(&escaped[start..i]));
+				if c == ';' {
+					out.push(vec![]);
+				}
+				start = i + 1;
+			}
+			_ => {}
+		}
+	}
+	out.last_mut().unwrap().push(unescape_text(&escaped[start..]));
+	out
+}
+
+//join_structured is the inverse of split_structured: it escapes the values and joins them with commas and
+// the components with semicolons. A component without values is written like one with a single empty value
+// (and no components like a single empty component), so split_structured returns `[""]` for it.
+pub fn join_structured<S: AsRef<str>>(components: &[Vec<S>]) -> String {
+	components.iter()
+			.map(|values| values.iter().map(|v| escape_text(v.as_ref())).collect::<Vec<_>>().join(","))
+			.collect::<Vec<_>>()
+			.join(";")
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::text::*;
+
+	#[test]
+	fn encode() {
+		assert_eq!(escape_text(""), "");
+		assert_eq!(escape_text("a"), "a");
+		assert_eq!(escape_text("\\"), "\\\\");
+		assert_eq!(escape_text(";"), "\\;");
+		assert_eq!(escape_text(","), "\\,");
+		assert_eq!(escape_text(":"), ":");
+		assert_eq!(escape_text("\n"), "\\n");
+		assert_eq!(escape_text("\r\n"), "\\n");
+		assert_eq!(escape_text("\r\n\r\n"), "\\n\\n");
+		assert_eq!(escape_text("\r\r\n"), "\\n\\n");
+		assert_eq!(escape_text("\\n"), "\\\\n");
+		assert_eq!(escape_text("a, b; c\\d\ne"), "a\\, b\\; c\\\\d\\ne");
+	}
+
+	#[test]
+	fn decode() {
+		assert_eq!(unescape_text(""), "");
+		assert_eq!(unescape_text("a"), "a");
+		assert_eq!(unescape_text("\\\\"), "\\");
+		assert_eq!(unescape_text("\\;"), ";");
+		assert_eq!(unescape_text("\\,"), ",");
+		assert_eq!(unescape_text("\\n"), "\n");
+		assert_eq!(unescape_text("\\N"), "\n");
+		assert_eq!(unescape_text("\\\\n"), "\\n");
+		assert_eq!(unescape_text("\\\\\\n"), "\\\n");
+		assert_eq!(unescape_text("\\:"), "\\:");
+		assert_eq!(unescape_text("a\\"), "a\\");
+		assert_eq!(unescape_text("a\\, b\\; c\\\\d\\ne"), "a, b; c\\d\ne");
+	}
+
+	#[test]
+	fn split() {
+		assert_eq!(split_structured(""), vec![vec![""]]);
+		assert_eq!(split_structured("a"), vec![vec!["a"]]);
+		assert_eq!(split_structured("a,b,c"), vec![vec!["a", "b", "c"]]);
+		assert_eq!(split_structured("Public;John;Q.;Dr.;Esq."), vec![vec!["Public"], vec!["John"], vec!["Q."], vec!["Dr."], vec!["Esq."]]);
+		assert_eq!(split_structured(";;123 Main Street;Any Town;CA;91921-1234;U.S.A."),
+			vec![vec![""], vec![""], vec!["123 Main Street"], vec!["Any Town"], vec!["CA"], vec!["91921-1234"], vec!["U.S.A."]]);
+		assert_eq!(split_structured("Stevenson;John;Philip,Paul;Dr.;Jr.,M.D.,A.C.P."),
+			vec![vec!["Stevenson"], vec!["John"], vec!["Philip", "Paul"], vec!["Dr."], vec!["Jr.", "M.D.", "A.C.P."]]);
+		assert_eq!(split_structured("a\\,b\\;c,d\\\\;e\\n"), vec![vec!["a,b;c", "d\\"], vec!["e\n"]]);
+		assert_eq!(split_structured(";"), vec![vec![""], vec![""]]);
+		assert_eq!(split_structured("a\\"), vec![vec!["a\\"]]);
+	}
+
+	#[test]
+	fn join() {
+		assert_eq!(join_structured::<&str>(&[]), "");
+		assert_eq!(join_structured(&[vec!["a", "b"]]), "a,b");
+		assert_eq!(join_structured(&[vec!["Public"], vec!["John"], vec![], vec!["Dr."]]), "Public;John;;Dr.");
+		assert_eq!(join_structured(&[vec!["a,b;c", "d\\"], vec!["e\n"]]), "a\\,b\\;c,d\\\\;e\\n");
+		let value = "Stevenson;John;Philip,Paul;Dr.;Jr.,M.D.,A.C.P.";
+		assert_eq!(join_structured(&split_structured(value)), value);
+	}
+
+	#[test]
+	fn round_trip() {
+		let components = vec![vec!["a,b", "c"], vec![""], vec!["d;e\\"]];
+		assert_eq!(split_structured(&join_structured(&components)), components);
+		//components without values come back with a single empty value
+		assert_eq!(split_structured(&join_structured(&[vec!["a"], vec![], vec!["b"]])), vec![vec!["a"], vec![""], vec!["b"]]);
+		assert_eq!(split_structured(&join_structured::<&str>(&[vec![]])), vec![vec![""]]);
+		assert_eq!(split_structured(&join_structured::<&str>(&[])), vec![vec![""]]);
+	}
+}